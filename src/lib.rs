@@ -0,0 +1,3555 @@
+#![feature(try_blocks)]
+#![feature(let_chains)]
+#![warn(clippy::pedantic, clippy::perf)]
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Error, Result};
+use clang::diagnostic::Severity;
+use clang::sonar::{find_functions, find_structs, find_typedefs};
+use clang::token::Token;
+use clang::Parser;
+use clang::{sonar, CompilationDatabase, Entity, EntityKind, Index, Type, TypeKind};
+use clap::Parser as ClapParser;
+use convert_case::{Case, Casing};
+use fxhash::{FxHashMap, FxHashSet};
+use itertools::{chain, Itertools};
+use lang_c::driver::{parse, Config};
+use serde::Serialize;
+
+/// How much is reported on stderr, see [`log!`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only errors
+    Quiet,
+    /// Also warnings and what was converted
+    Normal,
+    /// Also why each function was skipped, renamed or made a `const` method
+    Verbose,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// Sets the verbosity of the whole process, once at startup.
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+#[must_use]
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+/// `eprintln!` if the verbosity is at least `$level`, e.g.
+/// `log!(Verbose, "Skipping {name}")`.
+#[macro_export]
+macro_rules! log {
+    ($level:ident, $($arg:tt)*) => {
+        if $crate::verbosity() >= $crate::Verbosity::$level {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// How the headers are converted, see `--help` for each option.
+#[derive(clap::Args, Debug, Serialize)]
+#[allow(clippy::struct_excessive_bools)] // CLI flags
+pub struct Options {
+    /// C++ standard the generated code targets
+    #[arg(long, default_value_t = 17)]
+    pub cpp_standard: u32,
+    /// How wrappers of functions returning `HAL_StatusTypeDef` report the status
+    #[arg(long, value_enum, default_value_t)]
+    pub status_mode: StatusMode,
+    /// Shorthand for `--status-mode expected`
+    #[arg(long, conflicts_with = "status_mode")]
+    #[serde(skip)]
+    pub expected: bool,
+    /// Return the struct a method's only non-const struct pointer parameter points to instead of
+    /// taking the pointer, if it's the last parameter, e.g. `RTC_TimeTypeDef getTime(uint32_t
+    /// Format)`. Combined with the
+    /// status under `--status-mode bool`/`expected`/`throw`
+    #[arg(long)]
+    pub out_params: bool,
+    /// Take pointers to non-const structs, which the C functions require to be non-null, as
+    /// references, e.g. `setTime(RTC_TimeTypeDef &sTime, uint32_t Format)`. Arrays, `void *`
+    /// and the handle are still passed by pointer
+    #[arg(long)]
+    pub refs: bool,
+    /// Also wrap the peripheral's IRQ handlers, e.g. `HAL_UART_IRQHandler` as `handleIrq()`, to
+    /// call from the application's interrupt service routine
+    #[arg(long)]
+    pub irq_handlers: bool,
+    /// Default value of the trailing C parameters named PARAM in the wrapper declarations, e.g.
+    /// `--default-arg Timeout=HAL_MAX_DELAY`. Repeatable, and settable per peripheral in the
+    /// `--config`. It's an error for a function to have a parameter with a default followed by
+    /// one without, not counting the `--out-params` output parameter
+    #[arg(long = "default-arg", value_name = "PARAM=VALUE", value_parser = parse_default_arg)]
+    pub default_args: Vec<String>,
+    /// Declare the wrappers `noexcept`, unless they throw under `--status-mode throw`
+    #[arg(long)]
+    pub noexcept: bool,
+    /// Return the object from the methods wrapping functions that return a `HAL_StatusTypeDef`,
+    /// so calls chain, e.g. `uart.abort().transmit(...)`. Requires `--status-mode throw` or
+    /// `stored`, which report the status apart from the return value
+    #[arg(long)]
+    pub chain: bool,
+    /// Strip `struct`/`enum`/`union` elaborated specifiers from emitted types
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub strip_elaborated: bool,
+    /// How the generated namespaces are nested
+    #[arg(long, value_enum, default_value_t)]
+    pub namespace_layout: NamespaceLayout,
+    /// Where the generated files go in `outdir`
+    #[arg(long, value_enum, default_value_t)]
+    pub layout: OutputLayout,
+    /// Extension of the generated headers, e.g. `h` or `hxx`
+    #[arg(long, default_value = "hpp", value_name = "EXT")]
+    pub out_ext: String,
+    /// Prepended to the name of each generated header, e.g. `sthal_` for `sthal_hal_uart.hpp`
+    #[arg(long, default_value = "", value_name = "STR")]
+    pub out_prefix: String,
+    /// Combine the code of all modules into this one header instead
+    #[arg(long, value_name = "PATH")]
+    pub single_file: Option<PathBuf>,
+    /// How the generated headers guard against repeated inclusion
+    #[arg(long, value_enum, default_value_t)]
+    pub guard: IncludeGuard,
+    /// Outermost namespace used by the `nested` and `merged` layouts
+    #[arg(long, default_value = "stm32")]
+    pub root_namespace: String,
+    /// Namespace replacing `hal`/`ll` in the `flat` and `nested` layouts, e.g. `sthal`
+    #[arg(long, value_name = "NAME")]
+    pub namespace: Option<String>,
+    /// Emit an `at<Base>()` factory that `static_assert`s `Base` is a known instance of the
+    /// handle's register block. Instances are found from `#define TIM1 ((TIM_TypeDef *) TIM1_BASE)`
+    /// style macros, so this parses with a detailed preprocessing record (slower)
+    #[arg(long)]
+    pub instance_asserts: bool,
+    /// Non-peripheral HAL functions to emit into the shared `Core` namespace of every HAL header.
+    /// Functions without parameters are only wrapped when listed here, or as static methods if
+    /// they are named after the peripheral, e.g. `HAL_RCC_GetSysClockFreq`
+    #[arg(long, value_delimiter = ',', default_values_t = DEFAULT_GLOBAL_FUNCTIONS.map(String::from))]
+    pub global_functions: Vec<String>,
+    /// Emit a chained-setter builder for each of the peripheral's `*_InitTypeDef` structs
+    #[arg(long)]
+    pub init_builder: bool,
+    /// C standard to parse the HAL sources with (e.g. `c11`, `gnu11`). Without it clang uses its
+    /// own default, `gnu17` since clang 11 and `gnu11` before that
+    #[arg(long = "std", value_name = "STD")]
+    pub c_std: Option<String>,
+    /// Parse each file with its compile command that has an argument containing NAME, e.g. a
+    /// `-DSTM32F405xx` define or an output directory, when the compilation database has several
+    #[arg(long, value_name = "NAME")]
+    pub target: Option<String>,
+    /// Argument passed to clang for every file, e.g. `-I<dir>` or `-DSTM32F405xx`. Repeatable,
+    /// mostly useful without a compilation database
+    #[arg(long = "extra-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub extra_args: Vec<String>,
+    /// Mirror the peripheral's C enums as `enum class`es and take those in wrapper signatures.
+    /// HAL modules also get `HAL_StatusTypeDef` as `Status`
+    #[arg(long)]
+    pub enums: bool,
+    /// Re-export the object-like `<PERIPH>_...` macros of the peripheral's header as
+    /// `inline constexpr` values, e.g. `hal::Uart::WORDLENGTH_8B`. Like `--instance-asserts` this
+    /// parses with a detailed preprocessing record (slower)
+    #[arg(long)]
+    pub reexport_constants: bool,
+    /// Hide the handle of each class behind a pointer to an opaque `Impl`, with the method
+    /// definitions in a generated `.cpp` next to the header. Consumers no longer depend on the
+    /// handle struct's layout, at the cost of a heap allocation per object and of the methods no
+    /// longer being inline (calls only get inlined with LTO). The header still includes the
+    /// vendor header for the parameter types
+    #[arg(long)]
+    pub pimpl: bool,
+    /// Only declare the wrappers in the header, and define them in a generated `.cpp` next to
+    /// it, so including the header compiles faster. `consteval` functions, constructors and the
+    /// global functions stay inline
+    #[arg(long)]
+    pub split_impl: bool,
+    /// How HAL handle classes hold their handle. LL classes always store a pointer to the
+    /// peripheral's registers
+    #[arg(long, value_enum, default_value_t)]
+    pub embed_handle: EmbedHandle,
+    /// Name of the field holding the handle in each class, e.g. `m_handle`. Defaults to the
+    /// peripheral's name, e.g. `uart`
+    #[arg(long, value_name = "IDENT", value_parser = parse_identifier)]
+    pub handle_name: Option<String>,
+    /// Make the handle stored by each class private and add a `get_handle()` returning it, a
+    /// reference under `--embed-handle value`/`inherit`. `inherit` classes inherit privately
+    #[arg(long)]
+    pub encapsulate: bool,
+    /// Give handle classes a destructor calling the peripheral's `DeInit` function. Their copy
+    /// constructor and assignment are deleted so it only runs once per handle. Also adds a
+    /// constructor taking the `Init` config and calling the peripheral's `Init` function
+    #[arg(long)]
+    pub raii: bool,
+    /// Emit an `#error` when the header is compiled against a different HAL/CMSIS version than it
+    /// was generated from, by comparing the `__STM32..._VERSION_{MAIN,SUB1,SUB2,RC}` macros seen
+    /// while parsing. Parses with a detailed preprocessing record (slower)
+    #[arg(long)]
+    pub version_guard: bool,
+    /// Functions to emit as `consteval` copies of their C body instead of calling them, so
+    /// using them at runtime is a compile error (C++20). Eligibility isn't checked: the body
+    /// has to be valid C++ that only does constant arithmetic on its parameters, e.g. no register
+    /// access, calls to non-`constexpr` functions or implicit `void *` conversions. Only
+    /// functions that don't take a handle can be `consteval`, and they keep their C parameter
+    /// types even under `--enums`
+    #[arg(long, value_delimiter = ',', value_name = "FUNCTION")]
+    pub consteval: Vec<String>,
+    /// Functions returning a pointer that is never null, e.g. to a register block, whose
+    /// wrappers return a reference to the pointee instead. `void *` results stay pointers
+    #[arg(long, value_delimiter = ',', value_name = "FUNCTION")]
+    pub ref_returns: Vec<String>,
+    /// Don't write anything, instead fail if any output differs from the file on disk, like
+    /// `cargo fmt --check`. The first few out-of-date files are listed
+    #[arg(long)]
+    #[serde(skip)]
+    pub check: bool,
+    /// `--check`, also printing a unified diff of what regenerating would change to stderr
+    #[arg(long)]
+    #[serde(skip)]
+    pub check_diff: bool,
+    /// Convert everything but don't write anything, only report how many wrappers each module
+    /// would get
+    #[arg(long)]
+    #[serde(skip)]
+    pub dry_run: bool,
+    /// Don't regenerate headers whose source file, `_ex` sibling, `--functions-from` list,
+    /// options, HAL/LL overlap, `--namespace-from-path` roots and generator version are the same
+    /// as when they were generated, so their dependents aren't rebuilt. Their hash is kept in the
+    /// line after the `--banner-file` text of the generated header, or of its generated region
+    /// under `--append-mode`. Changes to other included headers go unnoticed. Skipped modules are
+    /// missing from `--report`
+    #[arg(long)]
+    #[serde(skip)]
+    pub skip_unchanged: bool,
+    /// Only replace the code between `// BEGIN sthal-generated` and `// END sthal-generated` in
+    /// existing files, keeping what was added around it by hand. Files without the markers are
+    /// overwritten, with a warning, and get them
+    #[arg(long)]
+    pub append_mode: bool,
+    /// Format each generated file with `clang-format`, in the style of the `.clang-format` that
+    /// applies to it. Files are left unformatted, with a warning, if it can't be run
+    #[arg(long)]
+    pub format: bool,
+    /// The `clang-format` executable `--format` runs, found on `PATH` by default
+    #[arg(long, value_name = "PATH")]
+    pub clang_format: Option<PathBuf>,
+    /// File whose contents are copied verbatim to the top of every generated file
+    #[arg(long, value_name = "FILE")]
+    pub banner_file: Option<PathBuf>,
+    /// The contents of `banner_file`
+    #[arg(skip)]
+    #[serde(skip)]
+    pub banner: String,
+    /// File listing the exact C functions to wrap, one per line (`#` starts a comment). Replaces
+    /// the `HAL_`/`LL_` prefix and IRQ handler/callback filters
+    #[arg(long, value_name = "FILE")]
+    pub functions_from: Option<PathBuf>,
+    /// The functions listed in `functions_from`
+    #[arg(skip)]
+    #[serde(skip)]
+    pub function_list: Option<FxHashSet<String>>,
+    /// Nest the namespaces inside ones named after the file's directory relative to its
+    /// `input`, e.g. `Src/stm32f4xx_hal_uart.c` -> `Src::hal::Uart`
+    #[arg(long)]
+    pub namespace_from_path: bool,
+    /// The directories `namespace_from_path` namespaces are relative to, the `input`s for the
+    /// CLI. The first one containing the file is used
+    #[arg(skip)]
+    #[serde(skip)]
+    pub path_roots: Vec<PathBuf>,
+    /// Declare a friend of every handle class, e.g. `--friend 'struct Registry<{class}>'`.
+    /// `{class}` is replaced by the class name. Peripherals without a handle are namespaces and
+    /// get no friends
+    #[arg(long = "friend", value_name = "DECL")]
+    pub friends: Vec<String>,
+    /// Warn about classes with more than N methods. `--verbose` also reports the method count of
+    /// the others
+    #[arg(long, value_name = "N")]
+    pub max_methods: Option<usize>,
+    /// Log each call through `--trace-macro` before forwarding it, in builds without `NDEBUG`
+    #[arg(long)]
+    pub trace: bool,
+    /// Macro called with the C function name by `--trace` wrappers. The default one is defined
+    /// in terms of `printf` unless the including code already defines it
+    #[arg(long, default_value = DEFAULT_TRACE_MACRO)]
+    pub trace_macro: String,
+    /// Fail instead of renaming/skipping methods whose derived name is empty or a C++ keyword
+    #[arg(long)]
+    pub strict_names: bool,
+    /// Indentation of each level of nesting: `tab` or a number of spaces
+    #[arg(long, default_value = "tab", value_name = "N|tab", value_parser = parse_indent)]
+    pub indent: String,
+}
+
+/// Validates an `--indent` value.
+fn parse_indent(arg: &str) -> Result<String, String> {
+    if arg == "tab" || arg.parse::<usize>().is_ok() {
+        Ok(arg.to_owned())
+    } else {
+        Err("expected `tab` or a number of spaces".to_owned())
+    }
+}
+
+/// Validates a `--default-arg` value.
+fn parse_default_arg(arg: &str) -> Result<String, String> {
+    match arg.split_once('=') {
+        Some((param, value)) if identifier(param) == param && !value.trim().is_empty() => {
+            Ok(arg.to_owned())
+        }
+        _ => Err("expected PARAM=VALUE".to_owned()),
+    }
+}
+
+/// Validates a `--handle-name` value.
+fn parse_identifier(arg: &str) -> Result<String, String> {
+    if identifier(arg) != arg || arg.starts_with("__") {
+        Err("expected a C++ identifier".to_owned())
+    } else if CPP_KEYWORDS.contains(&arg) {
+        Err(format!("`{arg}` is a C++ keyword"))
+    } else {
+        Ok(arg.to_owned())
+    }
+}
+
+impl Default for Options {
+    /// The options of a command line without any flags.
+    fn default() -> Self {
+        #[derive(ClapParser)]
+        struct Cli {
+            #[command(flatten)]
+            options: Options,
+        }
+        Cli::parse_from(["sthal_to_cpp"]).options
+    }
+}
+
+impl Options {
+    /// Applies shorthands, checks the options are compatible and reads the files they name.
+    ///
+    /// # Errors
+    /// Incompatible options, or a file that couldn't be read.
+    pub fn resolve(&mut self) -> Result<()> {
+        if self.expected {
+            self.status_mode = StatusMode::Expected;
+        }
+        if self.check_diff {
+            self.check = true;
+        }
+        if self.pimpl && self.single_file.is_some() {
+            bail!("--pimpl generates a `.cpp` per module, it can't be combined with --single-file");
+        }
+        if self.split_impl && self.single_file.is_some() {
+            bail!("--split-impl generates a `.cpp` per module, it can't be combined with --single-file");
+        }
+        if self.pimpl && self.handle_name.as_deref() == Some("impl") {
+            bail!("--pimpl classes already have an `impl` member, choose another --handle-name");
+        }
+        if self.pimpl && self.embed_handle != EmbedHandle::Pointer {
+            bail!("--pimpl keeps the handle in its `Impl`, it requires --embed-handle pointer");
+        }
+        if self.chain && !matches!(self.status_mode, StatusMode::Throw | StatusMode::Stored) {
+            bail!("--chain returns the object instead of the status, it requires --status-mode throw or stored");
+        }
+        if self.status_mode == StatusMode::Stored && !self.chain {
+            bail!("--status-mode stored keeps the status of --chain methods, it requires --chain");
+        }
+        if self.status_mode == StatusMode::Expected && self.cpp_standard < 23 {
+            bail!("--status-mode expected requires --cpp-standard 23");
+        }
+        if !self.consteval.is_empty() && self.cpp_standard < 20 {
+            bail!("--consteval requires --cpp-standard 20");
+        }
+        if let Some(banner_file) = &self.banner_file {
+            self.banner = std::fs::read_to_string(banner_file)
+                .with_context(|| format!("Could not read {}", banner_file.display()))?;
+        }
+        if let Some(functions_from) = &self.functions_from {
+            let list = std::fs::read_to_string(functions_from)
+                .with_context(|| format!("Could not read {}", functions_from.display()))?;
+            self.function_list = Some(
+                list.lines()
+                    .map(|line| line.split('#').next().unwrap_or_default().trim())
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_owned)
+                    .collect(),
+            );
+        }
+        Ok(())
+    }
+}
+
+pub const DEFAULT_TRACE_MACRO: &str = "STHAL_TRACE";
+
+/// The oldest libclang major version the parsing works with, the one the `clang` crate is
+/// built against.
+pub const MIN_LIBCLANG_VERSION: u32 = 10;
+
+/// The version of the loaded libclang, e.g. `Ubuntu clang version 14.0.0-1ubuntu1`. Older
+/// ones than [`MIN_LIBCLANG_VERSION`] tend to parse without errors but find nothing.
+///
+/// # Errors
+/// If libclang is older than [`MIN_LIBCLANG_VERSION`].
+pub fn libclang_version() -> Result<String> {
+    let version = clang::get_version();
+    let major = version
+        .split_whitespace()
+        .skip_while(|&word| word != "version")
+        .nth(1)
+        .and_then(|number| number.split('.').next()?.parse::<u32>().ok());
+    match major {
+        Some(major) if major < MIN_LIBCLANG_VERSION => bail!(
+            "{version} is too old, libclang {MIN_LIBCLANG_VERSION} or newer is required. Point LIBCLANG_PATH at a newer one"
+        ),
+        Some(_) => {}
+        None => log!(Normal, "[WARN] Unknown libclang version {version:?}"),
+    }
+    Ok(version)
+}
+
+pub const DEFAULT_GLOBS: [&str; 2] = ["*/*hal*.c", "*/*ll*.h"];
+
+pub const DEFAULT_GLOBAL_FUNCTIONS: [&str; 11] = [
+    "HAL_Init",
+    "HAL_DeInit",
+    "HAL_IncTick",
+    "HAL_Delay",
+    "HAL_GetTick",
+    "HAL_GetTickPrio",
+    "HAL_SetTickFreq",
+    "HAL_GetTickFreq",
+    "HAL_SuspendTick",
+    "HAL_ResumeTick",
+    "HAL_GetHalVersion",
+];
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NamespaceLayout {
+    /// `hal::Uart` for handle classes, a `hal::Gpio` namespace for peripherals without one.
+    /// Peripherals with several handle classes get a namespace too, e.g. `hal::Sai::SaiBlock`
+    #[default]
+    Flat,
+    /// Always `<root>::{hal|ll}::<Periph>`, with handle classes inside the peripheral namespace
+    Nested,
+    /// `<root>::<Periph>` for both HAL and LL. Peripherals wrapped by both get an extra
+    /// `<root>::<Periph>::{hal|ll}` level so their classes don't collide
+    Merged,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StatusMode {
+    /// Return the `HAL_StatusTypeDef` unchanged
+    #[default]
+    Raw,
+    /// Return `true` iff the status is `HAL_OK`
+    Bool,
+    /// Return `std::expected<void, HAL_StatusTypeDef>`, holding the status unless it's `HAL_OK`
+    /// (C++23)
+    Expected,
+    /// Return `void` and throw a `hal::Error` holding the status unless it's `HAL_OK`. Wrappers
+    /// aren't `--noexcept` then
+    Throw,
+    /// Keep the status of the last `--chain`ed method in the object, read with `last_status()`.
+    /// Static methods and functions return it unchanged
+    Stored,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmbedHandle {
+    /// Store a pointer to a handle owned elsewhere, e.g. by the generated `main.c`
+    #[default]
+    Pointer,
+    /// Store the handle struct itself
+    Value,
+    /// Derive from the handle struct, so the object is the handle
+    Inherit,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputLayout {
+    /// `<outdir>/hal_uart.hpp`
+    #[default]
+    Flat,
+    /// `<outdir>/stm32f4xx/hal_uart.hpp`, with the code inside a `stm32f4xx` namespace, so
+    /// several device families can share `outdir`
+    ByVersion,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IncludeGuard {
+    /// `#pragma once`
+    #[default]
+    Pragma,
+    /// `#ifndef`/`#define`/`#endif` with a macro named after the output file, e.g.
+    /// `HAL_UART_HPP_`
+    Ifndef,
+}
+
+/// The parts of a `<stver>_<hal_type>_<periph_type>.{c,h}` source file name. Invalid UTF-8 in
+/// the name is replaced, see [`output_path`] for the one place it matters.
+pub struct ModuleName {
+    /// The file name without its final extension
+    pub stem: String,
+    /// The device family, e.g. `stm32f4xx`
+    pub stver: String,
+    /// `<hal_type>_<periph_type>`
+    pub fname: String,
+    pub hal_type: String,
+    pub periph_type: String,
+}
+
+/// The vendor header the wrappers of module `name` include, without its `.h`: the family's
+/// `_hal.h` for HAL modules, which includes the enabled modules, the LL header itself for LL.
+fn vendor_header(name: &ModuleName) -> String {
+    if name.hal_type == "hal" {
+        format!("{}_hal", name.stver)
+    } else {
+        name.stem.clone()
+    }
+}
+
+/// Splits the name of a HAL/LL source file.
+///
+/// # Errors
+/// Files that don't follow the naming scheme, and `_ex` modules, which are converted with their
+/// base module.
+pub fn module_name(file: &Path) -> Result<ModuleName> {
+    if !matches!(extension(file), "c" | "h") {
+        bail!("Wrong extension");
+    }
+    let stem = file
+        .file_stem()
+        .context("Invalid filename")?
+        .to_string_lossy();
+    // Only the part before any further dots (`stm32f4xx_ll_tim.g.h`) follows the naming scheme
+    let ofname = stem.split('.').next().unwrap_or(&stem);
+
+    // The family and module type never contain `_`, the peripheral may (`tim_ex`)
+    let Some((stver, fname)) = ofname.split_once('_') else {
+        bail!("Invalid file name {ofname}")
+    };
+    let Some((hal_type, periph_type)) = fname.split_once('_') else {
+        bail!("Invalid file name {fname}")
+    };
+    if periph_type.is_empty() || periph_type == "ex" {
+        bail!("Invalid file name {fname}: no peripheral");
+    }
+    if periph_type.ends_with("_ex") {
+        bail!("Skipping extension module, already processed in main module")
+    }
+    if !["hal", "ll"].contains(&hal_type) {
+        bail!("Invalid hal_type {fname}");
+    }
+
+    Ok(ModuleName {
+        stem: stem.to_string(),
+        stver: stver.to_owned(),
+        fname: fname.to_owned(),
+        hal_type: hal_type.to_owned(),
+        periph_type: periph_type.to_owned(),
+    })
+}
+
+fn extension(file: &Path) -> &str {
+    file.extension().and_then(OsStr::to_str).unwrap_or_default()
+}
+
+/// The peripherals that have both a HAL and an LL module among `files`.
+#[must_use]
+pub fn hal_ll_overlap<'a>(files: impl Iterator<Item = &'a PathBuf>) -> FxHashSet<String> {
+    let mut hal = FxHashSet::default();
+    let mut ll = FxHashSet::default();
+    for file in files {
+        let Ok(name) = module_name(file) else {
+            continue;
+        };
+        if name.hal_type == "hal" {
+            hal.insert(name.periph_type);
+        } else {
+            ll.insert(name.periph_type);
+        }
+    }
+    hal.intersection(&ll).cloned().collect()
+}
+
+/// The header generated from the module `name` into `outdir`, named after it with the
+/// `--out-prefix` and `--out-ext`. The name must be valid UTF-8.
+#[must_use]
+pub fn output_path(name: &ModuleName, outdir: &Path, opts: &Options) -> PathBuf {
+    let dir = match opts.layout {
+        OutputLayout::Flat => outdir.to_owned(),
+        OutputLayout::ByVersion => outdir.join(&name.stver),
+    };
+    let ext = opts.out_ext.trim_start_matches('.');
+    dir.join(format!("{}{}.{ext}", opts.out_prefix, name.fname))
+}
+
+/// Warns about files that would be converted to the same output file, e.g. the same module of
+/// two device families in the flat layout. Only one of them is kept.
+pub fn warn_output_collisions<'a>(
+    files: impl Iterator<Item = &'a PathBuf>,
+    outdir: &Path,
+    opts: &Options,
+) {
+    let mut outputs: FxHashMap<PathBuf, Vec<&Path>> = FxHashMap::default();
+    for file in files {
+        if let Ok(name) = module_name(file) {
+            outputs
+                .entry(output_path(&name, outdir, opts))
+                .or_default()
+                .push(file);
+        }
+    }
+    for (output, files) in outputs.iter().sorted() {
+        if files.len() > 1 {
+            let files = files.iter().map(|file| file.display()).join(", ");
+            log!(
+                Normal,
+                "[WARN] {files} are all converted to {}, only one of them is kept",
+                output.display()
+            );
+        }
+    }
+}
+
+/// Converts the HAL/LL source `file` to a C++ header in `outdir`, see [`parse_file`]. Use that
+/// when converting several files, to name the classes of peripherals with both a HAL and an LL
+/// module apart under the `merged` namespace layout.
+///
+/// # Errors
+/// Files that don't follow the naming scheme, can't be parsed, or whose output can't be
+/// written.
+pub fn convert_header(
+    index: &Index,
+    db: Option<&CompilationDatabase>,
+    file: &Path,
+    outdir: &Path,
+    options: &Options,
+) -> Result<String> {
+    let converted = parse_file(
+        &ParseCache::new(index, db),
+        file,
+        outdir,
+        &FxHashSet::default(),
+        &Mutex::default(),
+        options,
+    )?;
+    Ok(converted.message)
+}
+
+/// A converted module.
+pub struct Converted {
+    /// What was converted to where
+    pub message: String,
+    /// The generated code, for `--single-file`
+    pub header: Header,
+    /// The errors clang reported parsing the module, the wrapper is likely incomplete if there
+    /// are any
+    pub parse_errors: Vec<String>,
+    /// What was generated, for `--report`. `None` for the modules skipped by `--skip-unchanged`
+    pub report: Option<ModuleReport>,
+    /// Whether `--skip-unchanged` found the output up to date and skipped the module
+    pub skipped: bool,
+}
+
+/// Converts the HAL/LL source `file` to a C++ header in `outdir`, or only generates the code
+/// for `--single-file`. `hal_ll_overlap` are the peripherals with both a HAL and an LL module among all the
+/// converted files, see [`hal_ll_overlap`]. The `--functions-from` functions found in the file
+/// are added to `listed_found`.
+///
+/// # Errors
+/// Files that don't follow the naming scheme, can't be parsed, or whose output can't be
+/// written.
+///
+/// # Panics
+/// If another thread panicked while holding `listed_found`.
+#[allow(clippy::implicit_hasher)]
+pub fn parse_file(
+    cache: &ParseCache,
+    file: &Path,
+    outdir: &Path,
+    hal_ll_overlap: &FxHashSet<String>,
+    listed_found: &Mutex<FxHashSet<String>>,
+    args: &Options,
+) -> Result<Converted> {
+    log!(Verbose, "Converting {}", file.display());
+    let name = module_name(file)?;
+    let ModuleName {
+        stver,
+        hal_type,
+        periph_type,
+        ..
+    } = &name;
+    let hal_ll_collision =
+        args.namespace_layout == NamespaceLayout::Merged && hal_ll_overlap.contains(periph_type);
+    let stamp = input_stamp(file, &name, hal_ll_collision, args)?;
+    if let Some(skipped) = skip_unchanged(file, &name, outdir, stamp.as_deref(), args) {
+        return Ok(skipped);
+    }
+
+    let hdr = cache
+        .parse(file, args)
+        .context("Could not parse the file")?;
+    // The extension module's functions (`HAL_RCCEx_...`) go into the same class/namespace
+    let ex_hdr = parse_ex_module(cache, file, &name, args)?;
+    let units = chain!([&hdr], &ex_hdr).collect_vec();
+    let parse_errors = units
+        .iter()
+        .flat_map(|unit| parse_errors(unit))
+        .collect_vec();
+    let functions = units
+        .iter()
+        .flat_map(|unit| find_functions(unit.get_entity().get_children()))
+        .unique_by(|decl| decl.name.clone())
+        .collect_vec();
+    record_listed(&functions, listed_found, args);
+    let instances = if args.instance_asserts {
+        find_instances(&hdr)
+    } else {
+        FxHashMap::default()
+    };
+
+    let handle_types = find_handle_types(hal_type, &hdr, periph_type, &functions);
+    let init_structs = if args.init_builder {
+        find_init_structs(&hdr, periph_type)
+    } else {
+        vec![]
+    };
+    let enums = if args.enums {
+        find_periph_enums(&hdr, periph_type)
+    } else {
+        vec![]
+    };
+    let status_enum = (args.enums && hal_type == "hal")
+        .then(|| find_enum(&hdr, "HAL_StatusTypeDef"))
+        .flatten();
+    let versions = if args.version_guard {
+        find_versions(&hdr)
+    } else {
+        vec![]
+    };
+    let constants = if args.reexport_constants {
+        find_constants(&hdr, hal_type, periph_type)
+    } else {
+        vec![]
+    };
+
+    let module = Module {
+        dir_namespaces: dir_namespaces(file, stver, args),
+        hal_ll_collision,
+        hal_type,
+        periph_type,
+        inc_name: vendor_header(&name),
+        functions,
+        handle_types,
+        instances,
+        init_structs,
+        enums,
+        status_enum,
+        constants,
+        versions,
+    };
+    let header = generate_header(&module, args)?;
+    let errors = errors_summary(&parse_errors);
+    let report = ModuleReport::new(file, &name, &header);
+    let is_utf8 = file.file_name().and_then(OsStr::to_str).is_some();
+    let file = file.display();
+    let message = if args.dry_run {
+        format!("{file}: {}{errors}", header_summary(periph_type, &header))
+    } else if let Some(single_file) = &args.single_file {
+        format!("{file} converted into {}{errors}", single_file.display())
+    } else {
+        // The output is named after the file, which would be mangled
+        if !is_utf8 {
+            bail!("{file}: Non-utf-8 filename");
+        }
+        let new_file = output_path(&name, outdir, args);
+        write_module(&module, &header, &new_file, outdir, stamp.as_deref(), args)?;
+        format!("{file} converted to {}{errors}", new_file.display())
+    };
+
+    Ok(Converted {
+        message,
+        header,
+        parse_errors,
+        report: Some(report),
+        skipped: false,
+    })
+}
+
+/// Writes the header generated for `module` to `new_file`, with the `--skip-unchanged` `stamp`
+/// following the `--banner-file` text, and its `.cpp` if it needs one.
+fn write_module(
+    module: &Module,
+    header: &Header,
+    new_file: &Path,
+    outdir: &Path,
+    stamp: Option<&str>,
+    opts: &Options,
+) -> Result<()> {
+    let relative = new_file.strip_prefix(outdir).unwrap_or(new_file);
+    let gen_code = render_headers(std::slice::from_ref(header), relative, opts)?;
+    let banner = banner(opts);
+    let body = gen_code
+        .strip_prefix(banner.as_str())
+        .expect("headers start with the banner");
+    let gen_code = format!("{banner}{}{body}", stamp.unwrap_or_default());
+    write_output(new_file, &gen_code, opts)?;
+    if opts.split_impl || (opts.pimpl && !module.handle_types.is_empty()) {
+        let header = new_file.file_name().context("Invalid filename")?;
+        let source = source_file(module, &header.to_string_lossy(), opts)?;
+        write_output(&new_file.with_extension("cpp"), &source, opts)?;
+    }
+    Ok(())
+}
+
+/// The line following the `--banner-file` text in the headers generated under
+/// `--skip-unchanged`, a hash of everything the output of `file` depends on apart from the
+/// included headers. `hal_ll_collision` is whether the module shares its peripheral with a module
+/// of the other HAL type, which only the other files converted tell. `None` without
+/// `--skip-unchanged` or when nothing is written per module.
+fn input_stamp(
+    file: &Path,
+    name: &ModuleName,
+    hal_ll_collision: bool,
+    opts: &Options,
+) -> Result<Option<String>> {
+    if !opts.skip_unchanged || opts.dry_run || opts.single_file.is_some() {
+        return Ok(None);
+    }
+    let mut hasher = fxhash::FxHasher64::default();
+    for source in chain!([file.to_owned()], Some(ex_module_file(file, name))) {
+        if source.exists() {
+            let contents = std::fs::read(&source)
+                .with_context(|| format!("Could not read {}", source.display()))?;
+            hasher.write(&contents);
+        }
+    }
+    // Everything that changes the output, as `--print-config` prints it
+    hasher.write(serde_json::to_string(opts)?.as_bytes());
+    hasher.write(opts.banner.as_bytes());
+    for function in opts.function_list.iter().flatten().sorted() {
+        hasher.write(function.as_bytes());
+        hasher.write_u8(b'\n');
+    }
+    // Not among the options printed, but they name the namespaces too
+    hasher.write_u8(hal_ll_collision.into());
+    if opts.namespace_from_path {
+        for root in &opts.path_roots {
+            hasher.write(root.as_os_str().as_encoded_bytes());
+            hasher.write_u8(0);
+        }
+    }
+    hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+    Ok(Some(format!(
+        "// Generated by sthal_to_cpp, inputs {:016x}\n",
+        hasher.finish()
+    )))
+}
+
+/// The module `file` when its output starts with `stamp` after the `--banner-file` text, i.e. it
+/// is up to date. Under `--append-mode` the generated region does instead. Never under `--check`, which
+/// compares the whole output.
+fn skip_unchanged(
+    file: &Path,
+    name: &ModuleName,
+    outdir: &Path,
+    stamp: Option<&str>,
+    opts: &Options,
+) -> Option<Converted> {
+    if opts.check {
+        return None;
+    }
+    let output = output_path(name, outdir, opts);
+    let old = std::fs::read_to_string(&output).ok()?;
+    let generated = if opts.append_mode {
+        let begin = old.find(BEGIN_GENERATED)? + BEGIN_GENERATED.len();
+        old[begin..].strip_prefix('\n')?
+    } else {
+        &old
+    };
+    let banner = banner(opts);
+    if !generated.strip_prefix(banner.as_str())?.starts_with(stamp?) {
+        return None;
+    }
+    Some(Converted {
+        message: format!(
+            "{} unchanged since {} was generated",
+            file.display(),
+            output.display()
+        ),
+        header: Header::default(),
+        parse_errors: vec![],
+        report: None,
+        skipped: true,
+    })
+}
+
+/// `UART: 14 methods, 1 handle class`
+fn header_summary(periph_type: &str, header: &Header) -> String {
+    let classes = match header.classes.len() {
+        1 => "1 handle class".to_owned(),
+        n => format!("{n} handle classes"),
+    };
+    let methods = header.method_count();
+    format!(
+        "{}: {methods} methods, {classes}",
+        periph_type.to_uppercase()
+    )
+}
+
+/// The namespaces `file`'s module goes in under `--layout by-version` and
+/// `--namespace-from-path`, outermost first.
+fn dir_namespaces(file: &Path, stver: &str, opts: &Options) -> Vec<String> {
+    let mut dir_namespaces = if opts.namespace_from_path {
+        path_namespaces(file, &opts.path_roots)
+    } else {
+        vec![]
+    };
+    if opts.layout == OutputLayout::ByVersion {
+        dir_namespaces.insert(0, identifier(stver));
+    }
+    dir_namespaces
+}
+
+/// The errors clang reported parsing `tu`, usually missing includes or defines that the
+/// compilation database should have supplied.
+fn parse_errors(tu: &clang::TranslationUnit) -> Vec<String> {
+    tu.get_diagnostics()
+        .into_iter()
+        .filter(|diag| matches!(diag.get_severity(), Severity::Error | Severity::Fatal))
+        .map(|diag| diag.to_string())
+        .collect_vec()
+}
+
+/// Appended to the log line of a converted module, naming the first few `parse_errors`.
+fn errors_summary(parse_errors: &[String]) -> String {
+    const SHOWN: usize = 3;
+    if parse_errors.is_empty() {
+        return String::new();
+    }
+    let first = parse_errors.iter().take(SHOWN).join("\n\t");
+    let more = if parse_errors.len() > SHOWN {
+        "\n\t..."
+    } else {
+        ""
+    };
+    format!(
+        ", with {} clang errors, check the include paths of the compilation database:\n\t{first}{more}",
+        parse_errors.len()
+    )
+}
+
+/// Adds the `--functions-from` functions among `functions` to `listed_found`.
+fn record_listed(
+    functions: &[sonar::Declaration],
+    listed_found: &Mutex<FxHashSet<String>>,
+    opts: &Options,
+) {
+    let Some(function_list) = &opts.function_list else {
+        return;
+    };
+    let mut listed_found = listed_found.lock().expect("not poisoned");
+    listed_found.extend(
+        functions
+            .iter()
+            .filter(|decl| function_list.contains(&decl.name))
+            .map(|decl| decl.name.clone()),
+    );
+}
+
+/// The error of a generated file that differs from the one on disk under `--check`.
+#[derive(Debug)]
+pub struct OutOfDate(pub PathBuf);
+
+impl std::fmt::Display for OutOfDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} is out of date", self.0.display())
+    }
+}
+
+impl std::error::Error for OutOfDate {}
+
+/// Writes a generated file, or under `--check` only compares it with the file on disk.
+/// Does nothing under `--dry-run`.
+///
+/// # Errors
+/// If the file couldn't be written or, under `--check`, [`OutOfDate`] if it differs.
+pub fn write_output(path: &Path, code: &str, opts: &Options) -> Result<()> {
+    if opts.dry_run {
+        return Ok(());
+    }
+    let mut code = clang_format(path, code, opts);
+    if opts.append_mode {
+        code = merge_generated(path, &code);
+    }
+    let code = code.as_str();
+    if opts.check {
+        let old = std::fs::read_to_string(path).unwrap_or_default();
+        if old != code {
+            if opts.check_diff {
+                let old_name = path.display().to_string();
+                let new_name = format!("{old_name} (generated)");
+                let diff = similar::TextDiff::from_lines(&old, code);
+                eprint!("{}", diff.unified_diff().header(&old_name, &new_name));
+            }
+            return Err(OutOfDate(path.to_owned()).into());
+        }
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create the output directory {}", dir.display()))?;
+    }
+    let file =
+        File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+    let mut file = BufWriter::new(file);
+    file.write_all(code.as_bytes())?;
+    Ok(())
+}
+
+/// The `_ex` sibling of the module `name` in `file`, which might not exist.
+fn ex_module_file(file: &Path, name: &ModuleName) -> PathBuf {
+    let ModuleName { stver, fname, .. } = name;
+    file.with_file_name(format!("{stver}_{fname}_ex.{}", extension(file)))
+}
+
+const BEGIN_GENERATED: &str = "// BEGIN sthal-generated";
+const END_GENERATED: &str = "// END sthal-generated";
+
+/// The `--append-mode` contents of `path`: `code` between the markers, in place of the code
+/// between them in the existing file.
+fn merge_generated(path: &Path, code: &str) -> String {
+    let newline = if code.ends_with('\n') { "" } else { "\n" };
+    let generated = format!("{BEGIN_GENERATED}\n{code}{newline}{END_GENERATED}\n");
+    let Ok(old) = std::fs::read_to_string(path) else {
+        return generated;
+    };
+    let markers = old.find(BEGIN_GENERATED).and_then(|begin| {
+        let end = begin + old[begin..].find(END_GENERATED)? + END_GENERATED.len();
+        Some((begin, end))
+    });
+    let Some((begin, end)) = markers else {
+        log!(
+            Normal,
+            "[WARN] {} has no `{BEGIN_GENERATED}`/`{END_GENERATED}` markers, overwriting it",
+            path.display()
+        );
+        return generated;
+    };
+    let after = &old[end..];
+    let after = after.strip_prefix('\n').unwrap_or(after);
+    format!("{}{generated}{after}", &old[..begin])
+}
+
+/// `code` formatted by `clang-format` under `--format`, in the style that applies to `path`.
+/// Unformatted if `clang-format` can't be run, which is only reported once.
+fn clang_format(path: &Path, code: &str, opts: &Options) -> String {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    if !opts.format {
+        return code.to_owned();
+    }
+    let program = opts
+        .clang_format
+        .as_deref()
+        .unwrap_or(Path::new("clang-format"));
+    run_clang_format(program, path, code).unwrap_or_else(|e| {
+        if !WARNED.swap(true, Ordering::Relaxed) {
+            log!(
+                Normal,
+                "[WARN] Could not run {}, leaving the output unformatted: {e}",
+                program.display()
+            );
+        }
+        code.to_owned()
+    })
+}
+
+/// Pipes `code` through the `clang-format` executable `program`.
+fn run_clang_format(program: &Path, path: &Path, code: &str) -> Result<String> {
+    let mut child = Command::new(program)
+        .arg(format!("--assume-filename={}", path.display()))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // clang-format reads all of its input before writing anything
+    let mut stdin = child.stdin.take().context("No stdin")?;
+    stdin.write_all(code.as_bytes())?;
+    drop(stdin);
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Parses the `_ex` sibling of the module `name`, if there is one.
+fn parse_ex_module<'a>(
+    cache: &ParseCache<'a>,
+    file: &Path,
+    name: &ModuleName,
+    opts: &Options,
+) -> Result<Option<Rc<clang::TranslationUnit<'a>>>> {
+    let ex_file = ex_module_file(file, name);
+    if !ex_file.exists() {
+        return Ok(None);
+    }
+    let ex_hdr = cache
+        .parse(&ex_file, opts)
+        .with_context(|| format!("Could not parse {}", ex_file.display()))?;
+    Ok(Some(ex_hdr))
+}
+
+/// Everything `parse_file` extracted from one HAL/LL translation unit.
+pub struct Module<'a> {
+    /// The device family's namespace for `--layout by-version`, followed by the namespaces from
+    /// the file's directory for `--namespace-from-path`
+    pub dir_namespaces: Vec<String>,
+    /// Both a HAL and an LL module wrap this peripheral into the same namespace
+    pub hal_ll_collision: bool,
+    pub hal_type: &'a str,
+    pub periph_type: &'a str,
+    /// The vendor header the generated code includes, without its `.h`
+    pub inc_name: String,
+    pub functions: Vec<sonar::Declaration<'a>>,
+    pub handle_types: Vec<(String, Type<'a>)>,
+    /// Register-block types to their instance base macros, see `find_instances`
+    pub instances: FxHashMap<String, Vec<String>>,
+    /// The peripheral's `*_InitTypeDef` structs, see `find_init_structs`
+    pub init_structs: Vec<sonar::Declaration<'a>>,
+    /// The peripheral's C enums to mirror as `enum class`es, see `find_periph_enums`
+    pub enums: Vec<sonar::Declaration<'a>>,
+    /// `HAL_StatusTypeDef`, mirrored along with the global functions
+    pub status_enum: Option<sonar::Declaration<'a>>,
+    /// The peripheral's constant macros to re-export, see `find_constants`
+    pub constants: Vec<String>,
+    /// Version macros and their values, see `find_versions`
+    pub versions: Vec<(String, String)>,
+}
+
+/// The `--banner-file` contents, ending with a newline unless empty.
+fn banner(args: &Options) -> String {
+    let mut code = args.banner.clone();
+    if !code.is_empty() && !code.ends_with('\n') {
+        code.push('\n');
+    }
+    code
+}
+
+/// What the generated header includes, `"stm32f4xx_hal_uart.h"` or `<cstdint>`. A set, so the
+/// includes of several modules are emitted sorted and only once.
+fn includes(module: &Module, args: &Options) -> BTreeSet<String> {
+    let Module {
+        inc_name,
+        handle_types,
+        instances,
+        enums,
+        status_enum,
+        ..
+    } = module;
+    let mut includes = BTreeSet::from([format!("\"{inc_name}.h\"")]);
+    if !instances.is_empty() {
+        includes.insert("<cstdint>".to_owned());
+    }
+    if args.status_mode == StatusMode::Expected {
+        includes.insert("<expected>".to_owned());
+    }
+    if args.out_params && args.status_mode == StatusMode::Bool {
+        includes.insert("<optional>".to_owned());
+    }
+    if args.pimpl && !handle_types.is_empty() {
+        includes.insert("<memory>".to_owned());
+    }
+    if !enums.is_empty() || status_enum.is_some() {
+        includes.insert("<type_traits>".to_owned());
+    }
+    // if hal_type == "hal" {
+    //     writeln!(code, "#include \"ll_{periph_type}.hpp\"")?;
+    // }
+    includes
+}
+
+/// The fallback trace macro and the version guard following the `includes`, each a separate
+/// item so those shared by several modules can be deduplicated.
+fn preamble(module: &Module, args: &Options) -> Vec<String> {
+    let Module {
+        inc_name, versions, ..
+    } = module;
+    let mut preamble = vec![];
+    if args.trace && args.trace_macro == DEFAULT_TRACE_MACRO {
+        preamble.push(format!(
+            "#ifndef {DEFAULT_TRACE_MACRO}\n#include <cstdio>\n#define {DEFAULT_TRACE_MACRO}(name) std::printf(\"%s\\n\", name)\n#endif\n"
+        ));
+    }
+    if !versions.is_empty() {
+        let mismatch = versions
+            .iter()
+            .map(|(name, value)| format!("{name} != {value}"))
+            .join(" || ");
+        preamble.push(format!(
+            "#if {mismatch}\n#error \"{inc_name}.h differs from the version these wrappers were generated from\"\n#endif\n"
+        ));
+    }
+    preamble
+}
+
+/// The generated code of one module, in parts so several modules can be combined into one
+/// header, see [`render_headers`].
+#[derive(Default)]
+pub struct Header {
+    /// The included headers, see `includes`
+    pub includes: BTreeSet<String>,
+    /// Preprocessor lines following the includes, see `preamble`
+    pub preamble: Vec<String>,
+    pub blocks: Vec<Block>,
+    /// The handle classes and their methods, for `--dry-run` and `--report`
+    pub classes: Vec<ClassReport>,
+    /// The wrappers outside of classes, when the module has no handle type
+    pub functions: Vec<MethodReport>,
+}
+
+impl Header {
+    /// The number of wrappers generated, in classes or not.
+    #[must_use]
+    pub fn method_count(&self) -> usize {
+        self.functions.len()
+            + self
+                .classes
+                .iter()
+                .map(|class| class.methods.len())
+                .sum::<usize>()
+    }
+}
+
+/// What was generated from one file, for `--report`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ModuleReport {
+    pub source: PathBuf,
+    pub peripheral: String,
+    pub hal_type: String,
+    pub stver: String,
+    pub classes: Vec<ClassReport>,
+    pub functions: Vec<MethodReport>,
+}
+
+impl ModuleReport {
+    fn new(file: &Path, name: &ModuleName, header: &Header) -> Self {
+        Self {
+            source: file.to_owned(),
+            peripheral: name.periph_type.clone(),
+            hal_type: name.hal_type.clone(),
+            stver: name.stver.clone(),
+            classes: header.classes.clone(),
+            functions: header.functions.clone(),
+        }
+    }
+}
+
+/// A generated handle class, for `--report`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ClassReport {
+    pub name: String,
+    pub methods: Vec<MethodReport>,
+}
+
+/// A generated wrapper, for `--report`.
+#[derive(Clone, Debug, Serialize)]
+pub struct MethodReport {
+    /// The wrapped C function
+    pub c_name: String,
+    pub name: String,
+    /// A `static` function rather than a method taking the object's handle
+    pub is_static: bool,
+    pub is_const: bool,
+    /// The C++ declaration, e.g. `HAL_StatusTypeDef transmit(const uint8_t *pData, uint16_t
+    /// Size, uint32_t Timeout)`
+    pub signature: String,
+}
+
+/// Code in a namespace.
+pub struct Block {
+    /// Outermost first
+    pub namespaces: Vec<String>,
+    /// Include guard of code that every header may emit but only one may define
+    pub guard: Option<String>,
+    pub code: String,
+}
+
+/// The C++ header wrapping `module`, to be written to `name` in the output directory.
+///
+/// # Errors
+/// Functions that can't be wrapped under `--strict-names`.
+pub fn generate_code(module: &Module, name: &Path, args: &Options) -> Result<String, Error> {
+    let header = generate_header(module, args)?;
+    render_headers(std::slice::from_ref(&header), name, args)
+}
+
+/// The parts of the C++ header wrapping `module`.
+///
+/// # Errors
+/// Functions that can't be wrapped under `--strict-names`.
+#[allow(clippy::missing_panics_doc)] // Only on broken invariants
+pub fn generate_header(module: &Module, args: &Options) -> Result<Header, Error> {
+    let &Module {
+        ref dir_namespaces,
+        hal_ll_collision,
+        hal_type,
+        periph_type,
+        ref functions,
+        ref handle_types,
+        ref init_structs,
+        ref enums,
+        ref constants,
+        ..
+    } = module;
+    let enum_classes = enums
+        .iter()
+        .map(|decl| (decl.name.clone(), enum_class_name(&decl.name)))
+        .collect::<FxHashMap<_, _>>();
+    let namespaces = namespace_path(
+        dir_namespaces,
+        hal_type,
+        periph_type,
+        handle_types.len() != 1,
+        hal_ll_collision,
+        args,
+    );
+    let mut code = String::new();
+    for decl in enums {
+        code.extend(enum_class(decl, &enum_classes[&decl.name], args));
+    }
+    // Single handle classes of the flat layout share the `hal`/`ll` namespace, so they get the
+    // constants as members instead
+    let constants_in_class =
+        handle_types.len() == 1 && args.namespace_layout == NamespaceLayout::Flat;
+    if !constants_in_class {
+        for macro_name in constants {
+            code.extend(constant(macro_name, periph_type, "inline"));
+        }
+    }
+    for init_struct in init_structs {
+        code.extend(init_builder(init_struct, args));
+    }
+    let (mut classes, mut static_methods) = (vec![], vec![]);
+    if handle_types.is_empty() {
+        let methods = static_functions(functions, hal_type, periph_type, &enum_classes, args)?;
+        check_method_count(
+            namespaces.last().expect("peripheral namespace"),
+            methods.len(),
+            args,
+        );
+        static_methods.extend(methods.iter().map(Method::report));
+        code.extend(methods.iter().map(|method| {
+            if args.split_impl && !method.is_consteval {
+                method.declaration()
+            } else {
+                method.inline()
+            }
+        }));
+    } else {
+        for (handle_typename, handle) in handle_types {
+            let (class, report) =
+                handle_class(module, handle_typename, handle, &enum_classes, args)?;
+            classes.extend(report);
+            code.push_str(&class);
+        }
+    }
+    let mut blocks = vec![];
+    // Every HAL header sees the shared declarations, so only the first include defines them
+    if hal_type == "hal" && args.status_mode == StatusMode::Throw {
+        // Before the classes throwing it
+        let namespaces = error_namespaces(args);
+        let guard = format!("STHAL_{}_ERROR", namespaces.join("_").to_uppercase());
+        blocks.push(Block {
+            namespaces,
+            guard: Some(guard),
+            code: "struct Error { HAL_StatusTypeDef status; };\n".to_owned(),
+        });
+    }
+    blocks.push(Block {
+        namespaces,
+        guard: None,
+        code,
+    });
+    if hal_type == "hal" {
+        let globals = global_functions(functions, &enum_classes, args)?;
+        let status = module
+            .status_enum
+            .as_ref()
+            .and_then(|decl| enum_class(decl, &enum_class_name(&decl.name), args));
+        if !globals.is_empty() || status.is_some() {
+            let namespaces = namespace_path(dir_namespaces, hal_type, "core", true, false, args);
+            let guard = format!("STHAL_{}", namespaces.join("_").to_uppercase());
+            blocks.push(Block {
+                namespaces,
+                guard: Some(guard),
+                code: chain!(status, globals.iter().map(Method::inline)).collect(),
+            });
+        }
+    }
+    Ok(Header {
+        includes: includes(module, args),
+        preamble: preamble(module, args),
+        blocks,
+        classes,
+        functions: static_methods,
+    })
+}
+
+/// The namespaces of the `--status-mode throw` exception, `hal` under the root namespace of the
+/// layout. Shared by all modules, whatever directory they're in.
+fn error_namespaces(opts: &Options) -> Vec<String> {
+    let type_ns = opts.namespace.as_deref().unwrap_or("hal").to_owned();
+    match opts.namespace_layout {
+        NamespaceLayout::Flat => vec![type_ns],
+        NamespaceLayout::Nested => vec![opts.root_namespace.clone(), type_ns],
+        NamespaceLayout::Merged => vec![opts.root_namespace.clone(), "hal".to_owned()],
+    }
+}
+
+/// The `--status-mode throw` exception, qualified from the global namespace so it's found from
+/// every class, e.g. `::hal::Error`.
+fn error_type(opts: &Options) -> String {
+    format!("::{}::Error", error_namespaces(opts).join("::"))
+}
+
+/// One header with the code of all `headers`, to be written to `name` in the output directory.
+/// Their includes are emitted sorted and their preamble items in order, each only once. Blocks
+/// in the same namespace are merged into one, in the order they first appear, and only the
+/// first block with a given guard is kept.
+///
+/// # Errors
+/// Never, `fmt::Write` for `String` doesn't fail.
+pub fn render_headers(headers: &[Header], name: &Path, args: &Options) -> Result<String, Error> {
+    use std::fmt::Write;
+    let mut code = banner(args);
+    let include_guard = include_guard(name);
+    match args.guard {
+        IncludeGuard::Pragma => writeln!(code, "#pragma once")?,
+        IncludeGuard::Ifndef => {
+            writeln!(code, "#ifndef {include_guard}")?;
+            writeln!(code, "#define {include_guard}")?;
+        }
+    }
+    let includes: BTreeSet<&String> = headers.iter().flat_map(|header| &header.includes).collect();
+    for include in includes {
+        writeln!(code, "#include {include}")?;
+    }
+    code.extend(
+        headers
+            .iter()
+            .flat_map(|header| &header.preamble)
+            .unique()
+            .map(String::as_str),
+    );
+    // Blocks merged by namespace and guard, in the order they first appear
+    let mut merged: Vec<(&[String], &Option<String>, String)> = vec![];
+    let mut guards = FxHashSet::default();
+    for block in headers.iter().flat_map(|header| &header.blocks) {
+        if block
+            .guard
+            .as_ref()
+            .is_some_and(|guard| !guards.insert(guard))
+        {
+            continue;
+        }
+        let same_place = merged.iter_mut().find(|(namespaces, guard, _)| {
+            *namespaces == block.namespaces.as_slice() && **guard == block.guard
+        });
+        match same_place {
+            Some((_, _, code)) => code.push_str(&block.code),
+            None => merged.push((&block.namespaces, &block.guard, block.code.clone())),
+        }
+    }
+    for (namespaces, guard, block) in merged {
+        if let Some(guard) = guard {
+            writeln!(code, "#ifndef {guard}")?;
+            writeln!(code, "#define {guard}")?;
+        }
+        code.push_str(&in_namespaces(namespaces, &block, args));
+        if guard.is_some() {
+            writeln!(code, "#endif")?;
+        }
+    }
+    if args.guard == IncludeGuard::Ifndef {
+        writeln!(code, "#endif // {include_guard}")?;
+    }
+    Ok(code)
+}
+
+/// `stm32f4xx/hal_uart.hpp` -> `STM32F4XX_HAL_UART_HPP_`
+fn include_guard(name: &Path) -> String {
+    identifier(&name.to_string_lossy()).to_uppercase() + "_"
+}
+
+/// `code` inside the nested `namespaces`, indented to match.
+fn in_namespaces(namespaces: &[String], code: &str, opts: &Options) -> String {
+    let opening = namespaces
+        .iter()
+        .enumerate()
+        .map(|(depth, ns)| format!("{}namespace {ns} {{\n", indent(depth, opts)));
+    let closing = (0..namespaces.len())
+        .rev()
+        .map(|depth| format!("{}}}\n", indent(depth, opts)));
+    chain!(
+        opening,
+        [indent_lines(code, namespaces.len(), opts)],
+        closing
+    )
+    .collect()
+}
+
+/// The indentation of code nested `depth` levels deep.
+fn indent(depth: usize, opts: &Options) -> String {
+    let level = match opts.indent.parse::<usize>() {
+        Ok(spaces) => " ".repeat(spaces),
+        Err(_) => "\t".to_owned(),
+    };
+    level.repeat(depth)
+}
+
+/// Indents each line of `code` by `depth` more levels, except preprocessor directives.
+fn indent_lines(code: &str, depth: usize, opts: &Options) -> String {
+    let indent = indent(depth, opts);
+    code.lines()
+        .map(|line| {
+            if line.is_empty() || line.starts_with('#') {
+                format!("{line}\n")
+            } else {
+                format!("{indent}{line}\n")
+            }
+        })
+        .collect()
+}
+
+/// The C enums belonging to the peripheral, named `<PERIPH>_...` or `HAL_<PERIPH>_...`.
+fn find_periph_enums<'a>(
+    hdr: &'a clang::TranslationUnit<'a>,
+    periph_type: &str,
+) -> Vec<sonar::Declaration<'a>> {
+    let prefix = periph_key(periph_type) + "_";
+    sonar::find_enums(hdr.get_entity().get_children())
+        .filter(|decl| {
+            let name = decl.name.strip_prefix("HAL_").unwrap_or(&decl.name);
+            name.starts_with(&prefix)
+        })
+        .collect_vec()
+}
+
+/// The C enum `name`, declared as `enum name {...}`, `typedef enum {...} name` or a typedef of
+/// another enum.
+fn find_enum<'a>(
+    hdr: &'a clang::TranslationUnit<'a>,
+    name: &str,
+) -> Option<sonar::Declaration<'a>> {
+    let children = hdr.get_entity().get_children();
+    if let Some(decl) = sonar::find_enums(children.clone()).find(|decl| decl.name == name) {
+        return Some(decl);
+    }
+    let typedef = find_typedefs(children).find(|decl| decl.name == name)?;
+    let entity = typedef
+        .entity
+        .get_typedef_underlying_type()?
+        .get_canonical_type()
+        .get_declaration()
+        .filter(|entity| entity.get_kind() == EntityKind::EnumDecl)?;
+    Some(sonar::Declaration {
+        name: typedef.name,
+        entity,
+        source: Some(typedef.entity),
+    })
+}
+
+/// `HAL_UART_StateTypeDef` -> `UartState`
+fn enum_class_name(c_name: &str) -> String {
+    let name = c_name.strip_prefix("HAL_").unwrap_or(c_name);
+    let name = name.strip_suffix("TypeDef").unwrap_or(name);
+    name.to_case(Case::UpperCamel)
+}
+
+/// An `enum class` with the same enumerators and values as the C enum `decl`, and `toC`/
+/// `to<Class>` functions converting between them.
+fn enum_class(decl: &sonar::Declaration, class_name: &str, opts: &Options) -> Option<String> {
+    use std::fmt::Write;
+    let c_name = &decl.name;
+    let mut code = String::new();
+    writeln!(
+        code,
+        "enum class {class_name} : std::underlying_type_t<{c_name}> {{"
+    )
+    .ok()?;
+    for constant in decl.entity.get_children() {
+        if constant.get_kind() != EntityKind::EnumConstantDecl {
+            continue;
+        }
+        let constant = constant.get_name()?;
+        writeln!(code, "{}{constant} = ::{constant},", indent(1, opts)).ok()?;
+    }
+    writeln!(code, "}};").ok()?;
+    writeln!(
+        code,
+        "constexpr {c_name} toC({class_name} value) {{ return static_cast<{c_name}>(value); }}"
+    )
+    .ok()?;
+    writeln!(
+        code,
+        "constexpr {class_name} to{class_name}({c_name} value) {{ return static_cast<{class_name}>(value); }}"
+    )
+    .ok()?;
+    Some(code)
+}
+
+/// The object-like `<PERIPH>_...` macros defined in the peripheral's own headers whose
+/// expansion can initialize a `constexpr` variable. Expansions casting to a pointer or
+/// containing statements are left out.
+fn find_constants(hdr: &clang::TranslationUnit, hal_type: &str, periph_type: &str) -> Vec<String> {
+    let prefix = periph_key(periph_type) + "_";
+    let module = format!("_{hal_type}_{periph_type}");
+    hdr.get_entity()
+        .get_children()
+        .into_iter()
+        .filter(|macro_def| {
+            macro_def.get_kind() == EntityKind::MacroDefinition
+                && !macro_def.is_function_like_macro()
+        })
+        .filter(|macro_def| {
+            macro_def
+                .get_location()
+                .and_then(|location| location.get_file_location().file)
+                .is_some_and(|file| {
+                    file.get_path()
+                        .file_stem()
+                        .is_some_and(|stem| stem.to_string_lossy().contains(&module))
+                })
+        })
+        .filter_map(|macro_def| {
+            let name = macro_def.get_name()?;
+            let tokens = macro_tokens(macro_def)?;
+            let (_, expansion) = tokens.split_first()?;
+            let is_constant = name.starts_with(&prefix)
+                && name.len() > prefix.len()
+                && !expansion.is_empty()
+                && !expansion
+                    .iter()
+                    .any(|t| ["{", ";", "*", "#"].contains(&t.as_str()));
+            is_constant.then_some(name)
+        })
+        .unique()
+        .collect_vec()
+}
+
+/// Re-exports the macro `macro_name` under its name without the peripheral prefix, e.g.
+/// `inline constexpr auto WORDLENGTH_8B = UART_WORDLENGTH_8B;`.
+fn constant(macro_name: &str, periph_type: &str, storage: &str) -> Option<String> {
+    let name = macro_name.get(periph_type.len() + 1..)?;
+    let mut name = identifier(name);
+    if CPP_KEYWORDS.contains(&name.as_str()) {
+        name.push('_');
+    }
+    Some(format!("{storage} constexpr auto {name} = {macro_name};\n"))
+}
+
+/// The peripheral's init structs (`UART_InitTypeDef`, `TIM_OC_InitTypeDef`, ...) with at least
+/// one field. They are found among the structs and typedef'd anonymous structs of the
+/// translation unit by their `<PERIPH>_` prefix and `_InitTypeDef` suffix.
+fn find_init_structs<'a>(
+    hdr: &'a clang::TranslationUnit<'a>,
+    periph_type: &str,
+) -> Vec<sonar::Declaration<'a>> {
+    let prefix = periph_key(periph_type) + "_";
+    find_structs(hdr.get_entity().get_children())
+        .filter(|decl| decl.name.starts_with(&prefix) && decl.name.ends_with("_InitTypeDef"))
+        .filter(|decl| {
+            decl.entity
+                .get_children()
+                .iter()
+                .any(|field| field.get_kind() == EntityKind::FieldDecl)
+        })
+        .collect_vec()
+}
+
+/// A chained-setter builder for an init struct, e.g. `UartInit().baudRate(9600).parity(...)`,
+/// that converts to the C struct.
+fn init_builder(init_struct: &sonar::Declaration, opts: &Options) -> Option<String> {
+    use std::fmt::Write;
+    let struct_name = &init_struct.name;
+    let builder = struct_name
+        .strip_suffix("TypeDef")?
+        .to_case(Case::UpperCamel);
+    let mut code = String::new();
+    writeln!(code, "struct {builder} {{").ok()?;
+    let indent = indent(1, opts);
+    writeln!(code, "{indent}{struct_name} init{{}};").ok()?;
+    for field in init_struct.entity.get_children() {
+        if field.get_kind() != EntityKind::FieldDecl {
+            continue;
+        }
+        // Arrays are left to be filled through `init`
+        let (Some(field_name), Some((ty, bit_width))) = (field.get_name(), field_type(field, opts))
+        else {
+            continue;
+        };
+        let setter = field_name.to_case(Case::Camel);
+        if let Some(width) = bit_width {
+            let bits = if width == 1 { "bit" } else { "bits" };
+            writeln!(
+                code,
+                "{indent}/** `{field_name}` is {width} {bits} wide, higher bits are dropped */"
+            )
+            .ok()?;
+        }
+        writeln!(
+            code,
+            "{indent}{builder} &{setter}({ty} value) {{ init.{field_name} = value; return *this; }}"
+        )
+        .ok()?;
+    }
+    writeln!(
+        code,
+        "{indent}operator {struct_name}() const {{ return init; }}"
+    )
+    .ok()?;
+    writeln!(code, "}};").ok()?;
+    Some(code)
+}
+
+/// The type of the struct member `field` as the parameter or result of an accessor, and its
+/// width if it's a bit-field. Bit-fields are declared `uint32_t Mode : 3;` but take their whole
+/// declared type, so values that don't fit are truncated when assigned, like in C. `None` for
+/// arrays, which can't be passed by value.
+fn field_type(field: Entity, opts: &Options) -> Option<(String, Option<usize>)> {
+    let ty = field.get_type()?;
+    if ty.get_kind() == TypeKind::ConstantArray {
+        return None;
+    }
+    // Unlike the declaration, the type doesn't include the width
+    let ty = emitted_type(ty.get_display_name(), opts);
+    Some((ty, field.get_bit_field_width()))
+}
+
+/// The class wrapping one of the module's handle types, with a method per function taking it,
+/// and its methods, unless the type has no class name.
+fn handle_class(
+    module: &Module,
+    handle_typename: &str,
+    handle: &Type,
+    enum_classes: &FxHashMap<String, String>,
+    args: &Options,
+) -> Result<(String, Option<ClassReport>), Error> {
+    use std::fmt::Write;
+    let &Module {
+        hal_type,
+        periph_type,
+        ref functions,
+        ref instances,
+        ref constants,
+        ..
+    } = module;
+    let Some(cname) = class_name(handle_typename) else {
+        log!(Normal, "[WARN] Weird handle type {handle_typename}");
+        return Ok((String::new(), None));
+    };
+    let handle_struct = handle_typename.trim_end_matches([' ', '*']);
+    let embed_handle = embed_handle(hal_type, args);
+    let mut code = String::new();
+    if embed_handle == EmbedHandle::Inherit {
+        let access = if args.encapsulate {
+            "private"
+        } else {
+            "public"
+        };
+        writeln!(code, "class {cname} : {access} {handle_struct} {{")?;
+    } else {
+        writeln!(code, "class {cname} {{")?;
+    }
+    // Access specifiers stay at the level of the class, the members are indented
+    let mut members = String::new();
+    for friend in &args.friends {
+        writeln!(members, "friend {};", friend.replace("{class}", &cname))?;
+    }
+    code.push_str(&indent_lines(&members, 1, args));
+    writeln!(code, "public:")?;
+    members.clear();
+    let (field, constructors) =
+        handle_storage(handle_typename, &cname, hal_type, periph_type, args);
+    // Under `--encapsulate` the stored handle is private, only reachable through `get_handle`
+    if !args.encapsulate {
+        members.extend(field.clone());
+    }
+    members.push_str(&constructors);
+    if let Some(bases) = instances.get(handle_struct) {
+        let valid = bases
+            .iter()
+            .map(|base| format!("Base == {base}"))
+            .join(" || ");
+        writeln!(members, "template <uintptr_t Base>")?;
+        writeln!(members, "static constexpr bool is_instance = {valid};")?;
+        writeln!(members, "template <uintptr_t Base>")?;
+        writeln!(
+            members,
+            "static {cname} at() {{ static_assert(is_instance<Base>, \"not a {handle_struct} instance\"); return {cname}(reinterpret_cast<{handle_typename}>(Base)); }}"
+        )?;
+    }
+    // Like the other `--pimpl` constructors, defined in the `.cpp`
+    let init = init_constructor(module, handle_typename, &cname, args);
+    members.extend(init.map(|init| init.in_class(args.pimpl)));
+    // The `--pimpl` destructor is defined in the `.cpp`
+    let deinit = deinit_call(module, handle_typename, args).filter(|_| !args.pimpl);
+    if let Some(deinit) = deinit {
+        writeln!(members, "~{cname}() {{ {deinit} }}")?;
+        writeln!(members, "{cname}(const {cname} &) = delete;")?;
+        writeln!(members, "{cname} &operator=(const {cname} &) = delete;")?;
+    }
+    if module.handle_types.len() == 1 && args.namespace_layout == NamespaceLayout::Flat {
+        for macro_name in constants {
+            members.extend(constant(macro_name, periph_type, "static"));
+        }
+    }
+    // if hal_type == "hal" {
+    //     writeln!(members, "template <bool dummy=true>")?;
+    //     writeln!(
+    //         members,
+    //         "ll::{cname} ll() {{ return ll::{cname}{{ {periph_type}->Instance }}; }}"
+    //     )?;
+    // }
+    // TODO: add virtual functions for callbacks
+    let methods = handle_functions(
+        functions,
+        handle_typename,
+        handle,
+        hal_type,
+        periph_type,
+        enum_classes,
+        args,
+    )?;
+    check_method_count(&cname, methods.len(), args);
+    members.extend(methods.iter().map(|method| method.member(args)));
+    let stored = stored_status(hal_type, args);
+    members.extend(stored.map(|(accessor, _)| accessor));
+    code.push_str(&indent_lines(&members, 1, args));
+    members.clear();
+    if args.encapsulate {
+        members.extend(field);
+    }
+    members.extend(stored.map(|(_, field)| field));
+    if args.pimpl {
+        writeln!(members, "struct Impl;")?;
+        writeln!(members, "std::unique_ptr<Impl> impl;")?;
+    }
+    if !members.is_empty() {
+        writeln!(code, "private:")?;
+        code.push_str(&indent_lines(&members, 1, args));
+    }
+    writeln!(code, "}};")?;
+    let report = ClassReport {
+        name: cname,
+        methods: methods.iter().map(Method::report).collect(),
+    };
+    Ok((code, Some(report)))
+}
+
+/// The `last_status()` accessor and the member it reads, which the `--chain` methods store
+/// their status in under `--status-mode stored`.
+fn stored_status(hal_type: &str, opts: &Options) -> Option<(&'static str, &'static str)> {
+    (hal_type == "hal" && opts.status_mode == StatusMode::Stored).then_some((
+        "HAL_StatusTypeDef last_status() const { return m_last_status; }\n",
+        "HAL_StatusTypeDef m_last_status = HAL_OK;\n",
+    ))
+}
+
+/// The `.cpp` of a `--pimpl` or `--split-impl` module, defining the `Impl` and the methods of
+/// its handle classes, or its functions if it has no handle. `header` is the file name of the
+/// generated header.
+fn source_file(module: &Module, header: &str, args: &Options) -> Result<String, Error> {
+    use std::fmt::Write;
+    let &Module {
+        ref dir_namespaces,
+        hal_ll_collision,
+        hal_type,
+        periph_type,
+        ref functions,
+        ref handle_types,
+        ref enums,
+        ..
+    } = module;
+    let enum_classes = enums
+        .iter()
+        .map(|decl| (decl.name.clone(), enum_class_name(&decl.name)))
+        .collect::<FxHashMap<_, _>>();
+    let mut code = banner(args);
+    writeln!(code, "#include \"{header}\"")?;
+    let namespaces = namespace_path(
+        dir_namespaces,
+        hal_type,
+        periph_type,
+        handle_types.len() != 1,
+        hal_ll_collision,
+        args,
+    );
+    let mut definitions = String::new();
+    for (handle_typename, handle) in handle_types {
+        let Some(cname) = class_name(handle_typename) else {
+            continue;
+        };
+        let code = &mut definitions;
+        if args.pimpl {
+            code.push_str(&pimpl_definitions(module, handle_typename, &cname, args)?);
+        }
+        let methods = handle_functions(
+            functions,
+            handle_typename,
+            handle,
+            hal_type,
+            periph_type,
+            &enum_classes,
+            args,
+        )?;
+        let scope = format!("{cname}::");
+        code.extend(
+            methods
+                .iter()
+                .filter(|method| !method.is_consteval)
+                .map(|method| method.definition(&scope)),
+        );
+    }
+    if handle_types.is_empty() {
+        let methods = static_functions(functions, hal_type, periph_type, &enum_classes, args)?;
+        definitions.extend(
+            methods
+                .iter()
+                .filter(|method| !method.is_consteval)
+                .map(|method| method.definition("")),
+        );
+    }
+    code.push_str(&in_namespaces(&namespaces, &definitions, args));
+    Ok(code)
+}
+
+/// The definitions of the `--pimpl` class `cname`'s `Impl` and of its constructors, destructor
+/// and move operations.
+fn pimpl_definitions(
+    module: &Module,
+    handle_typename: &str,
+    cname: &str,
+    args: &Options,
+) -> Result<String, Error> {
+    use std::fmt::Write;
+    let member = handle_member(module.periph_type, args);
+    let mut code = String::new();
+    writeln!(
+        code,
+        "struct {cname}::Impl {{ {handle_typename} {member}; }};"
+    )?;
+    writeln!(
+        code,
+        "{cname}::{cname}({handle_typename} {member}) : impl(new Impl{{{member}}}) {{}}"
+    )?;
+    if let Some(init) = init_constructor(module, handle_typename, cname, args) {
+        writeln!(code, "{}", init.definition(&format!("{cname}::")))?;
+    }
+    if let Some(deinit) = deinit_call(module, handle_typename, args) {
+        writeln!(code, "{cname}::~{cname}() {{ if (impl) {{ {deinit} }} }}")?;
+    } else {
+        writeln!(code, "{cname}::~{cname}() = default;")?;
+    }
+    if args.encapsulate {
+        writeln!(
+            code,
+            "{handle_typename} {cname}::get_handle() const {{ return impl->{member}; }}"
+        )?;
+    }
+    writeln!(code, "{cname}::{cname}({cname} &&) noexcept = default;")?;
+    writeln!(
+        code,
+        "{cname} &{cname}::operator=({cname} &&) noexcept = default;"
+    )?;
+    Ok(code)
+}
+
+/// The statement deinitializing the handle of a `--raii` class, calling the module's
+/// `HAL_<PERIPH>_DeInit` or `LL_<PERIPH>_DeInit` if it takes the handle.
+fn deinit_call(module: &Module, handle_typename: &str, opts: &Options) -> Option<String> {
+    if !opts.raii {
+        return None;
+    }
+    let &Module {
+        hal_type,
+        periph_type,
+        ref functions,
+        ref handle_types,
+        ..
+    } = module;
+    let deinit = format!(
+        "{}_{}_DeInit",
+        hal_type.to_uppercase(),
+        periph_type.to_uppercase()
+    );
+    let handle_type = handle_typename
+        .strip_prefix("__")
+        .unwrap_or(handle_typename);
+    let (_, handle) = handle_types
+        .iter()
+        .find(|(name, _)| name == handle_typename)?;
+    let decl = functions.iter().find(|decl| decl.name == deinit)?;
+    let first_type = decl.entity.get_arguments()?.first()?.get_type()?;
+    if !is_handle(&first_type, handle) {
+        return None;
+    }
+    let receiver = receiver(
+        handle_type,
+        handle_member(periph_type, opts),
+        hal_type,
+        opts,
+    );
+    Some(format!("{deinit}({receiver});"))
+}
+
+/// A `--raii` constructor initializing the peripheral, see `init_constructor`.
+struct InitConstructor {
+    class: String,
+    params: String,
+    /// The constructor delegated to, that stores the handle
+    delegate: String,
+    body: String,
+}
+
+impl InitConstructor {
+    /// The declaration, or the inline definition unless `declare_only`.
+    fn in_class(&self, declare_only: bool) -> String {
+        if declare_only {
+            format!("{}({});\n", self.class, self.params)
+        } else {
+            self.definition("") + "\n"
+        }
+    }
+
+    /// The definition, with the class name prefixed by `scope` out of the class.
+    fn definition(&self, scope: &str) -> String {
+        let InitConstructor {
+            class,
+            params,
+            delegate,
+            body,
+        } = self;
+        format!("{scope}{class}({params}) : {delegate} {{ {body} }}")
+    }
+}
+
+/// The `--raii` constructor of the class `cname` that stores the handle, sets its `Init` config
+/// and calls the module's init function, e.g. `Uart(UART_HandleTypeDef *uart, const
+/// UART_InitTypeDef &init)` calling `HAL_UART_Init`. Only if the module has exactly one
+/// `<HAL>_<PERIPH>_...Init` function taking just the handle, and the handle has an `Init`.
+fn init_constructor(
+    module: &Module,
+    handle_typename: &str,
+    cname: &str,
+    opts: &Options,
+) -> Option<InitConstructor> {
+    if !opts.raii {
+        return None;
+    }
+    let &Module {
+        hal_type,
+        periph_type,
+        ref functions,
+        ref handle_types,
+        ..
+    } = module;
+    let prefix = format!(
+        "{}_{}_",
+        hal_type.to_uppercase(),
+        periph_type.to_uppercase()
+    );
+    let handle_type = handle_typename
+        .strip_prefix("__")
+        .unwrap_or(handle_typename);
+    let (_, handle) = handle_types
+        .iter()
+        .find(|(name, _)| name == handle_typename)?;
+    let takes_handle = |decl: &&sonar::Declaration| {
+        let args = decl.entity.get_arguments().unwrap_or_default();
+        let [arg] = &args[..] else {
+            return false;
+        };
+        arg.get_type().is_some_and(|ty| is_handle(&ty, handle))
+    };
+    let mut inits = functions
+        .iter()
+        .filter(|decl| {
+            decl.name.starts_with(&prefix)
+                && decl.name.ends_with("Init")
+                && !decl.name.ends_with("DeInit")
+                && !decl.name.ends_with("MspInit")
+        })
+        .filter(takes_handle);
+    let (Some(init), None) = (inits.next(), inits.next()) else {
+        return None;
+    };
+    let config = handle
+        .get_fields()?
+        .into_iter()
+        .find(|field| field.get_name().as_deref() == Some("Init"))?
+        .get_type()?
+        .get_display_name();
+    let config = emitted_type(config, opts);
+    let handle_struct = handle_typename.trim_end_matches([' ', '*']);
+    let member = handle_member(periph_type, opts);
+    let (handle_param, delegate) = match embed_handle(hal_type, opts) {
+        EmbedHandle::Pointer => (
+            format!("{handle_typename} {member}"),
+            format!("{cname}({member})"),
+        ),
+        EmbedHandle::Value => (
+            format!("const {handle_struct} &{member}"),
+            format!("{cname}({member})"),
+        ),
+        EmbedHandle::Inherit => (
+            format!("const {handle_struct} &{member}"),
+            format!("{handle_struct}({member})"),
+        ),
+    };
+    let receiver = receiver(handle_type, member, hal_type, opts);
+    Some(InitConstructor {
+        class: cname.to_owned(),
+        params: format!("{handle_param}, const {config} &init"),
+        delegate,
+        body: format!("({receiver})->Init = init; {}({receiver});", init.name),
+    })
+}
+
+/// Whether the parameter type `ty` passes the handle of type `handle`, a pointer to it as
+/// stored by `find_handle_types` or the struct itself. Compares the structs declared, so
+/// typedefs of the handle match but types whose name merely starts like it don't.
+fn is_handle(ty: &Type, handle: &Type) -> bool {
+    handle_decl(ty).is_some_and(|decl| handle_decl(handle) == Some(decl))
+}
+
+/// The struct `ty` or the struct it points to, through typedefs and qualifiers.
+fn handle_decl<'a>(ty: &Type<'a>) -> Option<Entity<'a>> {
+    let ty = ty.get_pointee_type().unwrap_or(*ty).get_canonical_type();
+    let decl = ty
+        .get_declaration()
+        .filter(|_| ty.get_kind() == TypeKind::Record)?;
+    Some(decl.get_canonical_entity())
+}
+
+/// The name of the class wrapping a handle type, without its `_HandleTypeDef`/`_TypeDef` suffix,
+/// e.g. `__UART_HandleTypeDef *` -> `Uart`, `TIM_TypeDef *` -> `Tim`. `None` if that leaves
+/// nothing.
+#[must_use]
+pub fn class_name(handle_typename: &str) -> Option<String> {
+    let name = handle_typename.trim_end_matches([' ', '*']);
+    let name = name.strip_prefix("struct ").unwrap_or(name);
+    let name = name.trim_start_matches('_');
+    let name = name
+        .strip_suffix("_HandleTypeDef")
+        .or_else(|| name.strip_suffix("_TypeDef"))
+        // e.g. `USB_OTG_GlobalTypeDef`
+        .or_else(|| {
+            let (head, tail) = name.rsplit_once('_')?;
+            tail.ends_with("TypeDef").then_some(head)
+        })
+        .or_else(|| name.strip_suffix("TypeDef"))
+        .unwrap_or(name);
+    (!name.is_empty()).then(|| name.to_case(Case::UpperCamel))
+}
+
+/// A generated wrapper function, kept in parts so it can be emitted either inline or as a
+/// declaration with an out-of-line definition.
+#[allow(clippy::struct_excessive_bools)] // Independent qualifiers
+struct Method {
+    /// The wrapped C function
+    oname: String,
+    /// The C function's comment, see `doc_comment`
+    doc: String,
+    /// Declarations that have to precede the method, see `result_type`
+    alias: String,
+    is_static: bool,
+    /// Takes the handle as `const`, so doesn't modify the object
+    is_const: bool,
+    is_consteval: bool,
+    /// Can't throw, see `--noexcept`
+    is_noexcept: bool,
+    ret_type: String,
+    name: String,
+    params: String,
+    /// The `params` with their `--default-arg` values, for the declaration
+    declared_params: String,
+    /// The canonical parameter types, which tell overloads apart, see `signature`
+    signature: Vec<String>,
+    body: String,
+}
+
+impl Method {
+    fn inline(&self) -> String {
+        let Method {
+            doc,
+            alias,
+            ret_type,
+            name,
+            declared_params: params,
+            body,
+            ..
+        } = self;
+        let prefix = if self.is_static { "static " } else { "" };
+        let specifier = if self.is_consteval {
+            "consteval"
+        } else {
+            "inline"
+        };
+        let suffix = self.suffix();
+        format!(
+            "{doc}{alias}{prefix}{specifier} {ret_type} {name}({params}){suffix} {{ {body} }}\n"
+        )
+    }
+
+    fn declaration(&self) -> String {
+        // Defined in another file, so namespace functions can't be `static`
+        self.declared("")
+    }
+
+    /// The method in a class body, only declared if it's defined in the `.cpp`. Static methods
+    /// are declared `static` there.
+    fn member(&self, opts: &Options) -> String {
+        if (opts.pimpl || opts.split_impl) && !self.is_consteval {
+            self.declared(if self.is_static { "static " } else { "" })
+        } else {
+            self.inline()
+        }
+    }
+
+    fn declared(&self, prefix: &str) -> String {
+        let Method {
+            doc,
+            alias,
+            ret_type,
+            name,
+            declared_params: params,
+            ..
+        } = self;
+        let suffix = self.suffix();
+        format!("{doc}{alias}{prefix}{ret_type} {name}({params}){suffix};\n")
+    }
+
+    /// The out-of-line definition, `scope` qualifies the name, e.g. `Uart::` for methods.
+    fn definition(&self, scope: &str) -> String {
+        let Method {
+            alias,
+            ret_type,
+            name,
+            params,
+            body,
+            ..
+        } = self;
+        // A method's alias is a member, so it has to be qualified before the declarator
+        let ret_type = if alias.is_empty() {
+            ret_type.clone()
+        } else {
+            format!("{scope}{ret_type}")
+        };
+        let suffix = self.suffix();
+        format!("{ret_type} {scope}{name}({params}){suffix} {{ {body} }}\n")
+    }
+
+    fn report(&self) -> MethodReport {
+        MethodReport {
+            c_name: self.oname.clone(),
+            name: self.name.clone(),
+            is_static: self.is_static,
+            is_const: self.is_const,
+            signature: format!(
+                "{} {}({}){}",
+                self.ret_type,
+                self.name,
+                self.declared_params,
+                self.suffix()
+            ),
+        }
+    }
+
+    /// The qualifiers following the parameter list.
+    fn suffix(&self) -> &'static str {
+        match (self.is_const, self.is_noexcept) {
+            (false, false) => "",
+            (true, false) => " const",
+            (false, true) => " noexcept",
+            (true, true) => " const noexcept",
+        }
+    }
+}
+
+/// Whether the wrappers are declared `noexcept`, see `--noexcept`.
+fn is_noexcept(opts: &Options) -> bool {
+    opts.noexcept && opts.status_mode != StatusMode::Throw
+}
+
+/// Warns when the number of wrappers generated for `class` is over the `--max-methods` limit,
+/// and only reports it in verbose mode otherwise.
+fn check_method_count(class: &str, count: usize, opts: &Options) {
+    let Some(max_methods) = opts.max_methods else {
+        return;
+    };
+    if count > max_methods {
+        log!(
+            Normal,
+            "[WARN] {class} has {count} methods, more than {max_methods}"
+        );
+    } else {
+        log!(Verbose, "{class}: {count} methods");
+    }
+}
+
+/// The namespaces enclosing the generated code for one peripheral, outermost first. The flat
+/// layout only gives the peripheral a namespace if it's `grouped`: its functions without a
+/// handle class, or its several handle classes.
+fn namespace_path(
+    dir_namespaces: &[String],
+    hal_type: &str,
+    periph_type: &str,
+    grouped: bool,
+    hal_ll_collision: bool,
+    opts: &Options,
+) -> Vec<String> {
+    let periph_ns = periph_type.to_case(Case::UpperCamel);
+    let type_ns = opts.namespace.as_deref().unwrap_or(hal_type).to_owned();
+    let (root, inner) = match opts.namespace_layout {
+        NamespaceLayout::Flat if grouped => (None, vec![type_ns, periph_ns]),
+        NamespaceLayout::Flat => (None, vec![type_ns]),
+        NamespaceLayout::Nested => (Some(opts.root_namespace.clone()), vec![type_ns, periph_ns]),
+        // Keep the HAL and LL classes of a peripheral apart by moving them a level down
+        NamespaceLayout::Merged if hal_ll_collision => (
+            Some(opts.root_namespace.clone()),
+            vec![periph_ns, hal_type.to_owned()],
+        ),
+        NamespaceLayout::Merged => (Some(opts.root_namespace.clone()), vec![periph_ns]),
+    };
+    chain!(root, dir_namespaces.iter().cloned(), inner).collect_vec()
+}
+
+/// `<root>/Src/Periph/x.c` -> `["Src", "Periph"]`, each made a valid C++ identifier.
+fn path_namespaces(file: &Path, roots: &[PathBuf]) -> Vec<String> {
+    let Some(dir) = file
+        .parent()
+        .and_then(|dir| roots.iter().find_map(|root| dir.strip_prefix(root).ok()))
+    else {
+        return vec![];
+    };
+    dir.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(identifier(&name.to_string_lossy())),
+            _ => None,
+        })
+        .collect_vec()
+}
+
+/// Replaces everything that can't appear in a C++ identifier with `_`.
+fn identifier(name: &str) -> String {
+    let ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{ident}")
+    } else {
+        ident
+    }
+}
+
+/// The handle types of the module, each of which gets a class: the `*_HandleTypeDef`s for HAL
+/// modules and the register blocks the peripheral's functions take first for LL modules.
+///
+/// # Panics
+/// If `hal_type` is neither `hal` nor `ll`.
+#[must_use]
+pub fn find_handle_types<'a>(
+    hal_type: &str,
+    hdr: &'a clang::TranslationUnit<'a>,
+    periph_type: &str,
+    functions: &[sonar::Declaration<'a>],
+) -> Vec<(String, Type<'a>)> {
+    let handle_types = if hal_type == "hal" {
+        find_structs(hdr.get_entity().get_children())
+            .map(|decl| (decl.name, decl.entity.get_type().expect("Known type")))
+            .filter(|(decl, _)| decl.ends_with("_HandleTypeDef"))
+            .filter(|(decl, _)| mentions_periph(decl, periph_type))
+            // e.g. an anonymous struct with several typedef names
+            .unique_by(|(_, ty)| handle_decl(ty))
+            .map(|(decl, ty)| (decl + " *", ty))
+            .collect_vec()
+    } else if hal_type == "ll" {
+        functions
+            .iter()
+            .filter(|decl| mentions_periph(&decl.name, periph_type))
+            .filter_map(|decl| try {
+                let first = *decl
+                    .entity
+                    .get_arguments()
+                    .expect("known function")
+                    .first()?;
+                let ty = first.get_type()?;
+                // Getters take the register block as `const`, the class stores it mutable
+                let name = ty.get_display_name();
+                let name = name
+                    .strip_prefix("const ")
+                    .map_or(name.clone(), str::to_owned);
+                // The register block of another peripheral is still the instance when named
+                // after this one, e.g. `USART_TypeDef *LPUARTx`
+                let named_instance = first
+                    .get_name()
+                    .is_some_and(|arg| is_instance_name(&arg, periph_type));
+                (name, ty, named_instance)
+            })
+            .filter(|(name, _, _)| name.contains("_TypeDef"))
+            .filter(|(name, _, named_instance)| {
+                *named_instance || mentions_periph(name, periph_type)
+            })
+            .map(|(name, ty, _)| (name, ty))
+            // Also once for the getters taking the register block as `const`
+            .unique_by(|(_, ty)| handle_decl(ty))
+            .collect_vec()
+    } else {
+        unreachable!("Unknown hal_type {hal_type}");
+    };
+    handle_types
+}
+
+/// Whether the parameter `name` is how the LL functions name the instance of `periph`, e.g.
+/// `TIMx` and `USARTx`.
+fn is_instance_name(name: &str, periph: &str) -> bool {
+    name.strip_suffix('x')
+        .is_some_and(|name| name.eq_ignore_ascii_case(&periph_key(periph)))
+}
+
+/// The methods of the class of `handle_type`: the functions taking the handle as their first
+/// parameter, and as static methods the peripheral's functions without parameters, e.g.
+/// `HAL_RCC_GetSysClockFreq`. Other functions are skipped.
+fn handle_functions(
+    functions: &[sonar::Declaration],
+    handle_type: &str,
+    handle: &Type,
+    hal_type: &str,
+    periph_type: &str,
+    enums: &FxHashMap<String, String>,
+    opts: &Options,
+) -> Result<Vec<Method>> {
+    let handle_type = handle_type.strip_prefix("__").unwrap_or(handle_type);
+    functions
+        .iter()
+        .filter(|decl| is_wrapped(&decl.name, hal_type, opts))
+        .unique_by(|decl| function_key(decl))
+        .sorted_by_cached_key(|decl| source_position(decl))
+        .map(|decl| {
+            let oname = &decl.name;
+            let mut args = decl.entity.get_arguments().expect("known function");
+            if args.is_empty() {
+                return static_method(decl, periph_type, enums, opts);
+            }
+            let Some(first_type) = args[0].get_type() else {
+                log!(
+                    Verbose,
+                    "Skipping {oname}: unknown type of the first parameter"
+                );
+                return Ok(None);
+            };
+            // Functions only reading the handle take it as `const`, and become `const` methods
+            let is_const = first_type
+                .get_pointee_type()
+                .is_some_and(|pointee| pointee.is_const_qualified());
+            let (is_static, handle) = if is_handle(&first_type, handle) {
+                args.remove(0);
+                (
+                    false,
+                    vec![receiver(
+                        handle_type,
+                        handle_member(periph_type, opts),
+                        hal_type,
+                        opts,
+                    )],
+                )
+            }
+            // else if oname.contains(periph_up) {
+            //     ("static ", vec![])
+            // }
+            else {
+                return Ok(None);
+            };
+            let Some(name) = legal_name(method_name(oname, periph_type), oname, opts)? else {
+                return Ok(None);
+            };
+            if opts.consteval.contains(oname) {
+                log!(
+                    Normal,
+                    "[WARN] {oname} takes the handle, so it can't be consteval"
+                );
+            }
+            if is_const {
+                log!(
+                    Verbose,
+                    "{oname} takes a const handle, {name} is a const method"
+                );
+            }
+            let Some((alias, ret_type)) = result_type(decl, &name, opts) else {
+                return Ok(None);
+            };
+            let out = out_param(&mut args, &ret_type, opts);
+            // The output parameter is no longer a parameter of the wrapper
+            check_default_args(oname, &args, opts)?;
+            Ok(try {
+                let signature = signature(&args);
+                let names = args.iter().map(Entity::get_name).collect_vec();
+                let (params, mut call_args) = params(oname, args, enums, opts)?;
+                if let Some(out) = &out {
+                    call_args.insert(out.index, format!("&{}", out.name));
+                }
+                let call_args = chain!(handle, call_args).join(", ");
+                // `--chain` methods return the object, which a `const` method can't
+                let chained = opts.chain && out.is_none() && ret_type == "HAL_StatusTypeDef";
+                let cname = class_name(handle_type).filter(|_| chained);
+                let (ret_type, body) = forward_call(
+                    ret_type,
+                    oname,
+                    &call_args,
+                    out.as_ref(),
+                    cname.as_deref(),
+                    opts,
+                );
+
+                Method {
+                    oname: oname.clone(),
+                    doc: doc_comment(decl.entity),
+                    alias,
+                    is_static,
+                    is_const: is_const && !chained,
+                    is_consteval: false,
+                    is_noexcept: is_noexcept(opts),
+                    ret_type,
+                    name,
+                    declared_params: with_defaults(&params, &names, opts),
+                    params: params.join(", "),
+                    signature,
+                    body,
+                }
+            })
+        })
+        .flatten_ok()
+        .collect::<Result<_>>()
+        .map(dedupe_methods)
+}
+
+/// The static method wrapping `decl`, a function without parameters, if it belongs to the
+/// peripheral. Others, like `HAL_GetTick`, are only wrapped through `--global-functions`.
+fn static_method(
+    decl: &sonar::Declaration,
+    periph_type: &str,
+    enums: &FxHashMap<String, String>,
+    opts: &Options,
+) -> Result<Option<Method>> {
+    if mentions_periph(&decl.name, periph_type) {
+        return static_function(decl, periph_type, enums, opts);
+    }
+    log!(
+        Verbose,
+        "Skipping {}: takes no handle, list it in --global-functions to wrap it",
+        decl.name
+    );
+    Ok(None)
+}
+
+fn static_functions(
+    functions: &[sonar::Declaration],
+    hal_type: &str,
+    periph: &str,
+    enums: &FxHashMap<String, String>,
+    opts: &Options,
+) -> Result<Vec<Method>> {
+    functions
+        .iter()
+        .filter(|decl| is_wrapped(&decl.name, hal_type, opts))
+        .unique_by(|decl| function_key(decl))
+        .sorted_by_cached_key(|decl| source_position(decl))
+        .filter(|decl| mentions_periph(&decl.name, periph))
+        .map(|decl| static_function(decl, periph, enums, opts))
+        .flatten_ok()
+        .collect::<Result<_>>()
+        .map(dedupe_methods)
+}
+
+/// The comment documenting `function` in the C sources, re-indented for a class body, or
+/// nothing if it has none.
+fn doc_comment(function: Entity) -> String {
+    let Some(comment) = function.get_comment() else {
+        return String::new();
+    };
+    comment
+        .lines()
+        .map(str::trim)
+        .map(|line| {
+            // Line up the `*`s of block comments under the opening `/**`
+            if line.starts_with('*') {
+                format!(" {line}\n")
+            } else {
+                format!("{line}\n")
+            }
+        })
+        .collect()
+}
+
+/// Resolves methods that can't overload an earlier one, having the same name and parameter
+/// types, which a base and an extension function (`HAL_TIM_...`/`HAL_TIMEx_...`) can map to.
+/// Wrappers of extension functions get an `Ex` suffix, others are dropped.
+fn dedupe_methods(methods: Vec<Method>) -> Vec<Method> {
+    let mut seen = FxHashSet::default();
+    methods
+        .into_iter()
+        .filter_map(|mut method| {
+            if seen.insert((
+                method.name.clone(),
+                method.signature.clone(),
+                method.is_const,
+            )) {
+                return Some(method);
+            }
+            let renamed = format!("{}Ex", method.name);
+            if method.oname.contains("Ex_")
+                && seen.insert((renamed.clone(), method.signature.clone(), method.is_const))
+            {
+                log!(
+                    Verbose,
+                    "Renaming {}({}) of {} to {renamed}: defined twice",
+                    method.name,
+                    method.params,
+                    method.oname
+                );
+                method.name = renamed;
+                return Some(method);
+            }
+            log!(
+                Verbose,
+                "Skipping {}({}) of {}: defined twice",
+                method.name,
+                method.params,
+                method.oname
+            );
+            None
+        })
+        .collect()
+}
+
+/// The canonical types of the parameters `args`, the same for parameters spelled with
+/// different typedefs of a type.
+fn signature(args: &[Entity]) -> Vec<String> {
+    args.iter()
+        .map(|arg| {
+            arg.get_type()
+                .map(|ty| ty.get_canonical_type().get_display_name())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Whether `oname` is a function to wrap: one listed in `--functions-from` if given, otherwise
+/// any `HAL_`/`LL_` function that isn't an IRQ handler, unless `--irq-handlers`, or callback.
+/// Callbacks are defined by the application, but the functions registering them
+/// (`HAL_UART_RegisterCallback`, `HAL_UART_UnRegisterRxEventCallback`, ...) are wrapped.
+fn is_wrapped(oname: &str, hal_type: &str, opts: &Options) -> bool {
+    if let Some(function_list) = &opts.function_list {
+        return function_list.contains(oname);
+    }
+    let is_ll = hal_type == "ll";
+    let registers = oname.contains("_Register") || oname.contains("_UnRegister");
+    let is_callback = oname.ends_with("Callback") && !registers;
+    ((!is_ll && oname.starts_with("HAL_")) || (is_ll && oname.starts_with("LL_")))
+        && (opts.irq_handlers || !oname.ends_with("IRQHandler"))
+        && !is_callback
+}
+
+/// The name of the field holding a class's handle, see `--handle-name`.
+fn handle_member<'a>(periph_type: &'a str, opts: &'a Options) -> &'a str {
+    opts.handle_name.as_deref().unwrap_or(periph_type)
+}
+
+/// The argument passing the class's stored handle `member` of type `handle_type` to the C
+/// functions, which always take it by pointer. Under `--pimpl` the handle is stored in `impl`,
+/// and with `--embed-handle inherit` the object itself is the handle.
+fn receiver(handle_type: &str, member: &str, hal_type: &str, opts: &Options) -> String {
+    match embed_handle(hal_type, opts) {
+        EmbedHandle::Inherit => return "this".to_owned(),
+        EmbedHandle::Value => return format!("&this->{member}"),
+        EmbedHandle::Pointer => {}
+    }
+    let owner = if opts.pimpl { "impl" } else { "this" };
+    if handle_type.trim_end().ends_with('*') {
+        format!("{owner}->{member}")
+    } else {
+        format!("&{owner}->{member}")
+    }
+}
+
+/// The handle field a class of `handle_type` stores, if it isn't in the base class or the
+/// `--pimpl` `Impl`, and its constructors and `--encapsulate` accessor.
+fn handle_storage(
+    handle_type: &str,
+    cname: &str,
+    hal_type: &str,
+    periph_type: &str,
+    opts: &Options,
+) -> (Option<String>, String) {
+    let member = handle_member(periph_type, opts);
+    let handle_struct = handle_type.trim_end_matches([' ', '*']);
+    let embed_handle = embed_handle(hal_type, opts);
+    let field = match embed_handle {
+        EmbedHandle::Value => Some(format!("{handle_struct} {member};\n")),
+        EmbedHandle::Pointer if !opts.pimpl => Some(format!("{handle_type} {member};\n")),
+        _ => None,
+    };
+    let mut code = match embed_handle {
+        EmbedHandle::Inherit => format!("{cname}() : {handle_struct}{{}} {{}}\n"),
+        EmbedHandle::Value => format!(
+            "{cname}() : {member}{{}} {{}}\n{cname}(const {handle_struct} &{member}) : {member}({member}) {{}}\n"
+        ),
+        EmbedHandle::Pointer if opts.pimpl => format!(
+            "{cname}({handle_type} {member});\n~{cname}();\n{cname}({cname} &&) noexcept;\n{cname} &operator=({cname} &&) noexcept;\n"
+        ),
+        EmbedHandle::Pointer => {
+            format!("{cname}({handle_type} {member}) : {member}({member}) {{}}\n")
+        }
+    };
+    if opts.encapsulate {
+        code.push_str(&handle_getter(handle_type, member, hal_type, opts));
+        code.push('\n');
+    }
+    (field, code)
+}
+
+/// The `--encapsulate` accessor of a class's stored handle `member`: the handle pointer, or a
+/// reference to the handle when the class embeds it. Only declared under `--pimpl`.
+fn handle_getter(handle_type: &str, member: &str, hal_type: &str, opts: &Options) -> String {
+    let handle_struct = handle_type.trim_end_matches([' ', '*']);
+    match embed_handle(hal_type, opts) {
+        EmbedHandle::Inherit => format!("{handle_struct} &get_handle() {{ return *this; }}"),
+        EmbedHandle::Value => format!("{handle_struct} &get_handle() {{ return {member}; }}"),
+        EmbedHandle::Pointer if opts.pimpl => format!("{handle_type} get_handle() const;"),
+        EmbedHandle::Pointer => {
+            format!("{handle_type} get_handle() const {{ return {member}; }}")
+        }
+    }
+}
+
+/// The `--embed-handle` mode of a module's classes, LL classes always use a pointer.
+fn embed_handle(hal_type: &str, opts: &Options) -> EmbedHandle {
+    if hal_type == "ll" {
+        EmbedHandle::Pointer
+    } else {
+        opts.embed_handle
+    }
+}
+
+/// Orders the wrappers like the C declarations, independent of how clang traverses the headers,
+/// so regenerating gives the same output.
+fn source_position(decl: &sonar::Declaration) -> (Option<PathBuf>, u32) {
+    let Some(location) = decl.entity.get_location() else {
+        return (None, 0);
+    };
+    let location = location.get_file_location();
+    (location.file.map(|file| file.get_path()), location.offset)
+}
+
+/// Identifies a function across redeclarations, so each one is wrapped once.
+fn function_key(decl: &sonar::Declaration) -> String {
+    decl.entity
+        .get_usr()
+        .map_or_else(|| decl.name.clone(), |usr| usr.0)
+}
+
+/// Wrappers for the configured non-peripheral functions (`HAL_Delay`, `HAL_GetTick`, ...).
+fn global_functions(
+    functions: &[sonar::Declaration],
+    enums: &FxHashMap<String, String>,
+    opts: &Options,
+) -> Result<Vec<Method>> {
+    functions
+        .iter()
+        .filter(|decl| opts.global_functions.contains(&decl.name))
+        .unique_by(|decl| function_key(decl))
+        .map(|decl| static_function(decl, "core", enums, opts))
+        .flatten_ok()
+        .collect()
+}
+
+fn static_function(
+    decl: &sonar::Declaration,
+    periph: &str,
+    enums: &FxHashMap<String, String>,
+    opts: &Options,
+) -> Result<Option<Method>> {
+    let oname = &decl.name;
+    let Some(name) = legal_name(method_name(oname, periph), oname, opts)? else {
+        return Ok(None);
+    };
+    if opts.consteval.contains(oname) {
+        return consteval_function(decl, name, opts);
+    }
+    let args = decl.entity.get_arguments().expect("known function");
+    check_default_args(oname, &args, opts)?;
+    Ok(try {
+        let (alias, ret_type) = result_type(decl, &name, opts)?;
+        let signature = signature(&args);
+        let names = args.iter().map(Entity::get_name).collect_vec();
+        let (params, call_args) = params(oname, args, enums, opts)?;
+        let call_args = call_args.join(", ");
+        let (ret_type, body) = forward_call(ret_type, oname, &call_args, None, None, opts);
+
+        Method {
+            oname: oname.clone(),
+            doc: doc_comment(decl.entity),
+            alias,
+            is_static: true,
+            is_const: false,
+            is_consteval: false,
+            is_noexcept: is_noexcept(opts),
+            ret_type,
+            name,
+            declared_params: with_defaults(&params, &names, opts),
+            params: params.join(", "),
+            signature,
+            body,
+        }
+    })
+}
+
+/// A `--consteval` wrapper `name` with a copy of `decl`'s body, since the C function itself
+/// can't be called in a constant expression.
+fn consteval_function(
+    decl: &sonar::Declaration,
+    name: String,
+    opts: &Options,
+) -> Result<Option<Method>> {
+    let oname = &decl.name;
+    let definition = decl.entity.get_definition().unwrap_or(decl.entity);
+    let Some(body) = function_body(definition) else {
+        bail!("Could not find the body of --consteval function {oname}");
+    };
+    Ok(try {
+        let (alias, ret_type) = result_type(decl, &name, opts)?;
+        let args = decl.entity.get_arguments().expect("known function");
+        let params = args
+            .iter()
+            .map(|arg| emitted_type(arg.get_pretty_printer().print(), opts))
+            .join(", ");
+        Method {
+            oname: oname.clone(),
+            doc: doc_comment(decl.entity),
+            alias,
+            is_static: true,
+            is_const: false,
+            is_consteval: true,
+            is_noexcept: is_noexcept(opts),
+            ret_type,
+            name,
+            declared_params: params.clone(),
+            params,
+            signature: signature(&args),
+            body: body.trim().to_owned(),
+        }
+    })
+}
+
+/// The source text between the braces of a function definition. Function bodies aren't
+/// parsed, so this matches braces in the file itself, starting after the parameter list.
+fn function_body(function: Entity) -> Option<String> {
+    let location = function.get_location()?.get_file_location();
+    let source = std::fs::read_to_string(location.file?.get_path()).ok()?;
+    let rest = source.get(location.offset as usize..)?;
+    let params = rest.find('(')?;
+    let params_len = matching_close(&rest[params + 1..], '(', ')')?;
+    let rest = rest[params + params_len + 2..].trim_start();
+    let rest = rest.strip_prefix('{')?;
+    let body_len = matching_close(rest, '{', '}')?;
+    Some(rest[..body_len].to_owned())
+}
+
+/// The offset of the `close` ending the bracket opened just before `text`.
+fn matching_close(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            if depth == 0 {
+                return Some(i);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// `periph` as it's spelled in the C sources, e.g. `I2S` in `HAL_I2SEx_...` and
+/// `SPDIFRX_HandleTypeDef` for the `i2s` and `spdifrx` modules.
+fn periph_key(periph: &str) -> String {
+    periph.to_ascii_uppercase()
+}
+
+/// Whether the C name `name` belongs to `periph`, matched regardless of case so the
+/// lowercase module name finds `HAL_I2S_Init` and `__SAI_HandleTypeDef` alike.
+#[must_use]
+pub fn mentions_periph(name: &str, periph: &str) -> bool {
+    name.to_ascii_uppercase().contains(&periph_key(periph))
+}
+
+/// The method name of the C function `oname` of the module of `periph`, in camel case:
+/// - the `HAL_`/`LL_` prefix is dropped,
+/// - then one `<PERIPH>_` or `<PERIPH>Ex_` prefix, matched exactly, so `I2C_` isn't stripped
+///   from `I2C4_...`.
+///
+/// `HAL_UART_Transmit` -> `transmit`, `HAL_UARTEx_EnableStopMode` -> `enableStopMode`,
+/// `LL_TIM_SetCounter` -> `setCounter`. IRQ handlers are named after what they do,
+/// `HAL_UART_IRQHandler` -> `handleIrq`. May be empty or a C++ keyword, see `legal_name`.
+#[must_use]
+pub fn method_name(oname: &str, periph: &str) -> String {
+    let Some((_, name)) = oname.split_once('_') else {
+        return String::new();
+    };
+    let periph_up = periph_key(periph);
+    let name = [format!("{periph_up}Ex_"), format!("{periph_up}_")]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix.as_str()))
+        .unwrap_or(name);
+    // `IRQHandler` -> `handleIrq`, `NMI_IRQHandler` -> `handleNmiIrq`
+    if let Some(source) = name.strip_suffix("IRQHandler") {
+        return format!("handle_{source}_irq").to_case(Case::Camel);
+    }
+    name.to_case(Case::Camel)
+}
+
+#[rustfmt::skip]
+const CPP_KEYWORDS: [&str; 97] = [
+    "alignas", "alignof", "and", "and_eq", "asm", "atomic_cancel", "atomic_commit",
+    "atomic_noexcept", "auto", "bitand", "bitor", "bool", "break", "case", "catch", "char",
+    "char8_t", "char16_t", "char32_t", "class", "compl", "concept", "const", "consteval",
+    "constexpr", "constinit", "const_cast", "continue", "co_await", "co_return", "co_yield",
+    "decltype", "default", "delete", "do", "double", "dynamic_cast", "else", "enum", "explicit",
+    "export", "extern", "false", "float", "for", "friend", "goto", "if", "inline", "int", "long",
+    "mutable", "namespace", "new", "noexcept", "not", "not_eq", "nullptr", "operator", "or",
+    "or_eq", "private", "protected", "public", "reflexpr", "register", "reinterpret_cast",
+    "requires", "return", "short", "signed", "sizeof", "static", "static_assert", "static_cast",
+    "struct", "switch", "synchronized", "template", "this", "thread_local", "throw", "true", "try",
+    "typedef", "typeid", "typename", "union", "unsigned", "using", "virtual", "void", "volatile",
+    "wchar_t", "while", "xor", "xor_eq",
+];
+
+/// Checks a derived method name can be declared in C++. Names colliding with a keyword get a
+/// trailing `_` and empty names are skipped, unless `--strict-names` makes both an error.
+fn legal_name(name: String, oname: &str, opts: &Options) -> Result<Option<String>> {
+    if name.is_empty() {
+        if opts.strict_names {
+            bail!("{oname} has no method name left after stripping its prefixes");
+        }
+        log!(
+            Verbose,
+            "Skipping {oname}: no method name left after stripping its prefixes"
+        );
+        return Ok(None);
+    }
+    if CPP_KEYWORDS.contains(&name.as_str()) {
+        if opts.strict_names {
+            bail!("{oname} maps to the C++ keyword `{name}`");
+        }
+        return Ok(Some(name + "_"));
+    }
+    Ok(Some(name))
+}
+
+/// The return type spelling for the wrapper `name` of `decl`, and any declaration that has to
+/// precede the wrapper. Function pointer declarators can't be put in front of a method name, so
+/// those get a `using` alias. `--ref-returns` pointers become references. Returns `None` for
+/// types a function can't return in C++.
+fn result_type(decl: &sonar::Declaration, name: &str, opts: &Options) -> Option<(String, String)> {
+    let ty = decl.entity.get_result_type().expect("known function");
+    let spelling = emitted_type(ty.get_display_name(), opts);
+    let pointee_kind = ty
+        .get_pointee_type()
+        .map(|pointee| pointee.get_canonical_type().get_kind());
+    match (ty.get_kind(), pointee_kind) {
+        (TypeKind::Pointer, Some(TypeKind::FunctionPrototype | TypeKind::FunctionNoPrototype)) => {
+            let alias = name.to_case(Case::UpperCamel) + "Result";
+            Some((format!("using {alias} = {spelling};\n"), alias))
+        }
+        (TypeKind::Pointer, Some(kind)) if opts.ref_returns.contains(&decl.name) => {
+            if kind == TypeKind::Void {
+                log!(
+                    Normal,
+                    "[WARN] {} returns `void *`, which can't be returned as a reference",
+                    decl.name
+                );
+                return Some((String::new(), spelling));
+            }
+            let pointee = ty.get_pointee_type().expect("pointer");
+            Some((
+                String::new(),
+                format!("{} &", emitted_type(pointee.get_display_name(), opts)),
+            ))
+        }
+        (
+            TypeKind::ConstantArray
+            | TypeKind::IncompleteArray
+            | TypeKind::VariableArray
+            | TypeKind::DependentSizedArray,
+            _,
+        ) => {
+            log!(
+                Verbose,
+                "Skipping {}: array return type {spelling}",
+                decl.name
+            );
+            None
+        }
+        _ => Some((String::new(), spelling)),
+    }
+}
+
+/// The wrapper parameter declarations and forwarded arguments for the C parameters `args` of
+/// `oname`, see `param`. `None`, after reporting why, if one of them can't be wrapped.
+fn params(
+    oname: &str,
+    args: Vec<Entity>,
+    enums: &FxHashMap<String, String>,
+    opts: &Options,
+) -> Option<(Vec<String>, Vec<String>)> {
+    let mut params = vec![];
+    let mut call_args = vec![];
+    for (i, arg) in args.into_iter().enumerate() {
+        let Some((param, call_arg)) = param(i, arg, enums, opts) else {
+            log!(Verbose, "Skipping {oname}: can't declare parameter {i}");
+            return None;
+        };
+        params.push(param);
+        call_args.push(call_arg);
+    }
+    Some((params, call_args))
+}
+
+/// The declaration of the wrapper parameter for the `i`th C parameter and the expression
+/// forwarding it to the C function. Parameters of a mirrored enum type take the `enum class` and
+/// are cast back at the call, under `--refs` struct pointers take a reference whose address is
+/// forwarded. Unnamed parameters, common in prototypes, are named `arg{i}`. `None` if the
+/// parameter's type is unknown.
+fn param(
+    i: usize,
+    arg: Entity,
+    enums: &FxHashMap<String, String>,
+    opts: &Options,
+) -> Option<(String, String)> {
+    let spelling = arg.get_type()?.get_display_name();
+    let (decl, name) = if let Some(name) = arg.get_name() {
+        (emitted_type(arg.get_pretty_printer().print(), opts), name)
+    } else {
+        let name = format!("arg{i}");
+        (
+            declarator(&emitted_type(spelling.clone(), opts), &name),
+            name,
+        )
+    };
+    if let Some(enum_class) = enums.get(&emitted_type(spelling.clone(), opts)) {
+        return Some((
+            format!("{enum_class} {name}"),
+            format!("static_cast<{spelling}>({name})"),
+        ));
+    }
+    // Array parameters decay to pointers, but may point to several elements
+    let pointee = arg
+        .get_type()
+        .filter(|ty| opts.refs && ty.get_kind() == TypeKind::Pointer && !spelling.contains('['))
+        .as_ref()
+        .and_then(mutable_struct_pointee);
+    if let Some(pointee) = pointee {
+        let pointee = emitted_type(pointee.get_display_name(), opts);
+        return Some((format!("{pointee} &{name}"), format!("&{name}")));
+    }
+    Some((decl, name))
+}
+
+/// The `--default-arg` value of the parameter `name`.
+fn default_arg<'a>(name: &str, opts: &'a Options) -> Option<&'a str> {
+    opts.default_args.iter().find_map(|arg| {
+        let (param, value) = arg.split_once('=')?;
+        (param == name).then_some(value)
+    })
+}
+
+/// Fails if a parameter of `oname` with a `--default-arg` is followed by one without, which
+/// C++ doesn't allow.
+fn check_default_args(oname: &str, args: &[Entity], opts: &Options) -> Result<()> {
+    let has_default = |arg: &Entity| {
+        arg.get_name()
+            .is_some_and(|name| default_arg(&name, opts).is_some())
+    };
+    let Some(first) = args.iter().position(has_default) else {
+        return Ok(());
+    };
+    if let Some(missing) = args[first..].iter().find(|arg| !has_default(arg)) {
+        bail!(
+            "--default-arg for {} of {oname} is followed by {}, which has no default",
+            args[first].get_name().unwrap_or_default(),
+            missing.get_name().unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+/// The wrapper parameter declarations `params` of the C parameters `names`, with their
+/// `--default-arg` values.
+fn with_defaults(params: &[String], names: &[Option<String>], opts: &Options) -> String {
+    params
+        .iter()
+        .zip(names)
+        .map(
+            |(param, name)| match name.as_deref().and_then(|name| default_arg(name, opts)) {
+                Some(value) => format!("{param} = {value}"),
+                None => param.clone(),
+            },
+        )
+        .join(", ")
+}
+
+/// An output parameter returned by the wrapper instead, see `--out-params`.
+struct OutParam {
+    /// Position among the parameters after the handle
+    index: usize,
+    /// The struct pointed to
+    ty: String,
+    name: String,
+}
+
+/// Removes the output parameter from `args` under `--out-params`. That's the only pointer to
+/// a non-const struct, if it's the last parameter, of a function returning nothing or a status
+/// the status mode can combine with the struct.
+fn out_param(args: &mut Vec<Entity>, ret_type: &str, opts: &Options) -> Option<OutParam> {
+    let combines = ret_type == "void"
+        || (ret_type == "HAL_StatusTypeDef"
+            && !matches!(opts.status_mode, StatusMode::Raw | StatusMode::Stored));
+    if !opts.out_params || !combines {
+        return None;
+    }
+    let pointees = args
+        .iter()
+        .map(|arg| arg.get_type().as_ref().and_then(mutable_struct_pointee))
+        .collect_vec();
+    let mut candidates = pointees.iter().positions(Option::is_some);
+    let (Some(index), None) = (candidates.next(), candidates.next()) else {
+        return None;
+    };
+    // Struct pointers followed by other parameters are more likely inputs, e.g. a config
+    if index + 1 != args.len() {
+        return None;
+    }
+    let ty = emitted_type(pointees[index]?.get_display_name(), opts);
+    // Unnamed parameters are named by position, which shifts once this one is removed
+    let name = args.remove(index).get_name().unwrap_or("out".to_owned());
+    Some(OutParam { index, ty, name })
+}
+
+/// The struct that `ty`, a pointer to a non-const struct, points to.
+fn mutable_struct_pointee<'a>(ty: &Type<'a>) -> Option<Type<'a>> {
+    let pointee = ty.get_pointee_type()?;
+    let is_struct = pointee.get_canonical_type().get_kind() == TypeKind::Record;
+    (is_struct && !pointee.is_const_qualified()).then_some(pointee)
+}
+
+/// Declares `name` with the type spelled `ty`. Function pointer and array declarators wrap the
+/// name, `void (*)(int)` -> `void (*name)(int)`, `uint8_t[4]` -> `uint8_t name[4]`.
+fn declarator(ty: &str, name: &str) -> String {
+    if let Some(pointer) = ty.find("(*") {
+        let (head, tail) = ty.split_at(pointer + 2);
+        format!("{head}{name}{tail}")
+    } else if let Some(array) = ty.find('[') {
+        let (element, extent) = ty.split_at(array);
+        format!("{} {name}{extent}", element.trim_end())
+    } else {
+        format!("{ty} {name}")
+    }
+}
+
+/// Builds the return type and body of a wrapper that forwards to `oname`, translating
+/// `HAL_StatusTypeDef` results according to the selected status mode, and returning the `out`
+/// parameter. Methods of the class `chain` return the object instead of the status, see
+/// `--chain`.
+fn forward_call(
+    ret_type: String,
+    oname: &str,
+    call_args: &str,
+    out: Option<&OutParam>,
+    chain: Option<&str>,
+    opts: &Options,
+) -> (String, String) {
+    let call = format!("{oname}({call_args})");
+    let (ret_type, body) = match (out, opts.status_mode, chain) {
+        (Some(out), _, _) => out_call(&ret_type, &call, out, opts),
+        // The C function returns a pointer, see `result_type`
+        _ if ret_type.ends_with('&') => (ret_type, format!("return *{call};")),
+        _ if ret_type != "HAL_StatusTypeDef" => (ret_type, format!("return {call};")),
+        (_, StatusMode::Throw, Some(cname)) => (
+            format!("{cname} &"),
+            format!("if (auto status = {call}; status != HAL_OK) {{ throw {}{{status}}; }} return *this;", error_type(opts)),
+        ),
+        (_, StatusMode::Stored, Some(cname)) => (
+            format!("{cname} &"),
+            format!("m_last_status = {call}; return *this;"),
+        ),
+        (_, StatusMode::Raw | StatusMode::Stored, _) => (ret_type, format!("return {call};")),
+        (_, StatusMode::Bool, _) => ("bool".to_owned(), format!("return {call} == HAL_OK;")),
+        (_, StatusMode::Expected, _) => (
+            "std::expected<void, HAL_StatusTypeDef>".to_owned(),
+            format!(
+                "if (auto status = {call}; status != HAL_OK) {{ return std::unexpected(status); }} return {{}};"
+            ),
+        ),
+        (_, StatusMode::Throw, _) => (
+            "void".to_owned(),
+            format!("if (auto status = {call}; status != HAL_OK) {{ throw {}{{status}}; }}", error_type(opts)),
+        ),
+    };
+    if opts.trace {
+        let trace_macro = &opts.trace_macro;
+        let indent = indent(1, opts);
+        let body = format!(
+            "\n#ifndef NDEBUG\n{indent}{trace_macro}(\"{oname}\");\n#endif\n{indent}{body}\n"
+        );
+        return (ret_type, body);
+    }
+    (ret_type, body)
+}
+
+/// The return type and body of a wrapper returning its output parameter `out`, see
+/// `out_param`.
+fn out_call(ret_type: &str, call: &str, out: &OutParam, opts: &Options) -> (String, String) {
+    let OutParam { ty, name, .. } = out;
+    let local = format!("{ty} {name}{{}};");
+    match opts.status_mode {
+        _ if ret_type == "void" => (ty.clone(), format!("{local} {call}; return {name};")),
+        StatusMode::Bool => (
+            format!("std::optional<{ty}>"),
+            format!("{local} if ({call} != HAL_OK) {{ return std::nullopt; }} return {name};"),
+        ),
+        StatusMode::Expected => (
+            format!("std::expected<{ty}, HAL_StatusTypeDef>"),
+            format!(
+                "{local} if (auto status = {call}; status != HAL_OK) {{ return std::unexpected(status); }} return {name};"
+            ),
+        ),
+        StatusMode::Throw => (
+            ty.clone(),
+            format!(
+                "{local} if (auto status = {call}; status != HAL_OK) {{ throw {}{{status}}; }} return {name};",
+                error_type(opts)
+            ),
+        ),
+        StatusMode::Raw | StatusMode::Stored => {
+            unreachable!("out_param doesn't combine with raw or stored statuses")
+        }
+    }
+}
+
+/// Applies the type-spelling normalizations selected in `opts` to a rendered C type or
+/// parameter declaration.
+fn emitted_type(ty: String, opts: &Options) -> String {
+    if opts.strip_elaborated {
+        strip_elaborated(&ty)
+    } else {
+        ty
+    }
+}
+
+/// Removes elaborated type specifiers, e.g. `const struct __UART_HandleTypeDef *huart`
+/// becomes `const __UART_HandleTypeDef *huart`. In C++ the tag name alone names the type.
+fn strip_elaborated(ty: &str) -> String {
+    let mut out = String::with_capacity(ty.len());
+    let mut rest = ty;
+    while !rest.is_empty() {
+        let word_len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if word_len == 0 {
+            let c = rest.chars().next().expect("non-empty");
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let (word, tail) = rest.split_at(word_len);
+        if ["struct", "enum", "union"].contains(&word) && tail.starts_with(' ') {
+            rest = tail.trim_start();
+        } else {
+            out.push_str(word);
+            rest = tail;
+        }
+    }
+    out
+}
+
+/// Maps register-block types to the `_BASE` address macros of their instances, found from
+/// definitions like `#define TIM1 ((TIM_TypeDef *) TIM1_BASE)`.
+fn find_instances(hdr: &clang::TranslationUnit) -> FxHashMap<String, Vec<String>> {
+    let mut instances: FxHashMap<String, Vec<String>> = FxHashMap::default();
+    for macro_def in hdr.get_entity().get_children() {
+        if macro_def.get_kind() != EntityKind::MacroDefinition || macro_def.is_function_like_macro()
+        {
+            continue;
+        }
+        let Some(tokens) = macro_tokens(macro_def) else {
+            continue;
+        };
+        let [_, open, open_cast, ty, star, close_cast, base, close] = &tokens[..] else {
+            continue;
+        };
+        if [open, open_cast, star, close_cast, close] == ["(", "(", "*", ")", ")"]
+            && base.ends_with("_BASE")
+        {
+            let bases = instances.entry(ty.clone()).or_default();
+            if !bases.contains(base) {
+                bases.push(base.clone());
+            }
+        }
+    }
+    instances
+}
+
+/// The `__STM32..._VERSION_{MAIN,SUB1,SUB2,RC}` macros of the HAL and CMSIS headers with
+/// their literal values, e.g. `("__STM32F4xx_CMSIS_VERSION_MAIN", "(0x02U)")`.
+fn find_versions(hdr: &clang::TranslationUnit) -> Vec<(String, String)> {
+    hdr.get_entity()
+        .get_children()
+        .into_iter()
+        .filter(|macro_def| {
+            macro_def.get_kind() == EntityKind::MacroDefinition
+                && !macro_def.is_function_like_macro()
+        })
+        .filter_map(|macro_def| {
+            let tokens = macro_tokens(macro_def)?;
+            let (name, value) = tokens.split_first()?;
+            let is_version = name.to_uppercase().starts_with("__STM32")
+                && ["_MAIN", "_SUB1", "_SUB2", "_RC"]
+                    .iter()
+                    .any(|part| name.ends_with(&format!("_VERSION{part}")));
+            let literal = match value {
+                [literal] => literal,
+                [open, literal, close] if open == "(" && close == ")" => literal,
+                _ => return None,
+            };
+            let is_literal = literal.starts_with(|c: char| c.is_ascii_digit());
+            (is_version && is_literal).then(|| (name.clone(), value.join("")))
+        })
+        .unique_by(|(name, _)| name.clone())
+        .collect_vec()
+}
+
+/// The spellings of a macro definition's tokens, starting with its name.
+fn macro_tokens(macro_def: Entity) -> Option<Vec<String>> {
+    let mut tokens = macro_def
+        .get_range()?
+        .tokenize()
+        .iter()
+        .map(Token::get_spelling)
+        .collect_vec();
+    // Older libclang versions include the following `#` in the macro's range
+    if tokens.last().is_some_and(|t| t == "#") {
+        tokens.pop();
+    }
+    Some(tokens)
+}
+
+/// The arguments of a compile command that affect what the header parses to: macros, include
+/// paths, prefix headers and the language standard. Outputs, `-c`, the source file, warnings,
+/// precompiled headers etc. are dropped.
+#[must_use]
+pub fn preprocessor_args(args: Vec<String>) -> Vec<String> {
+    const PREFIXES: [&str; 4] = ["-D", "-U", "-I", "-std="];
+    // Flags whose value can be the next argument
+    const SEPARATE: [&str; 4] = ["-D", "-U", "-I", "-include"];
+    // Flags taking the next argument that are dropped with it
+    const SEPARATE_DROPPED: [&str; 1] = ["-include-pch"];
+    let mut kept = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // `-include<path>`, but not another flag like `-include-pch`
+        let is_joined_include = arg
+            .strip_prefix("-include")
+            .is_some_and(|path| !path.is_empty() && !path.starts_with(['-', '=']));
+        if SEPARATE.contains(&arg.as_str()) {
+            if let Some(value) = args.next() {
+                kept.extend([arg, value]);
+            }
+        } else if SEPARATE_DROPPED.contains(&arg.as_str()) {
+            args.next();
+        } else if is_joined_include || PREFIXES.iter().any(|prefix| arg.starts_with(prefix)) {
+            kept.push(arg);
+        }
+    }
+    kept
+}
+
+/// The arguments of the compile command of `file` among `commands`: the first one with an
+/// argument containing `--target`, or the first one if none has.
+fn compile_command(
+    commands: &[clang::CompileCommand],
+    file: &Path,
+    opts: &Options,
+) -> Option<Vec<String>> {
+    let commands = commands
+        .iter()
+        .map(clang::CompileCommand::get_arguments)
+        .collect_vec();
+    let matching = opts.target.as_ref().map_or(vec![], |target| {
+        commands
+            .iter()
+            .filter(|args| args.iter().any(|arg| arg.contains(target)))
+            .collect_vec()
+    });
+    let (candidates, which) = if matching.is_empty() {
+        (commands.iter().collect_vec(), "")
+    } else {
+        (matching, " matching --target")
+    };
+    if candidates.len() > 1 {
+        log!(
+            Verbose,
+            "{} has {} compile commands{which}, using the first",
+            file.display(),
+            candidates.len(),
+        );
+    }
+    candidates.first().map(|&args| args.clone())
+}
+
+/// The translation units parsed for the files with one canonical path, so a file converted
+/// again, e.g. matched by overlapping `--glob`s, and the `_ex` sibling it parses aren't
+/// reparsed. Keyed by canonical path and the parse arguments, which `[peripherals.<periph>]`
+/// options can change. The headers a file includes are parsed as part of each unit, so the
+/// units are only kept as long as the cache. Borrows the `Index` the units belong to, so it
+/// can't outlive it.
+pub struct ParseCache<'a> {
+    index: &'a Index<'a>,
+    db: Option<&'a CompilationDatabase>,
+    units: RefCell<FxHashMap<UnitKey, Rc<clang::TranslationUnit<'a>>>>,
+    hits: Cell<usize>,
+}
+
+/// A file's canonical path, the arguments clang parses it with and whether it keeps the macro
+/// definitions, see `ParseCache`.
+type UnitKey = (PathBuf, Vec<String>, bool);
+
+impl<'a> ParseCache<'a> {
+    #[must_use]
+    pub fn new(index: &'a Index<'a>, db: Option<&'a CompilationDatabase>) -> Self {
+        Self {
+            index,
+            db,
+            units: RefCell::default(),
+            hits: Cell::new(0),
+        }
+    }
+
+    /// The translation unit of `file`, parsed with [`parse_header`] unless it already was with
+    /// the same arguments.
+    ///
+    /// # Errors
+    /// If clang couldn't parse the file.
+    pub fn parse(
+        &self,
+        file: &Path,
+        opts: &Options,
+    ) -> std::result::Result<Rc<clang::TranslationUnit<'a>>, clang::SourceError> {
+        let path = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        let key = (
+            path,
+            parse_args(self.db, file, opts),
+            detailed_preprocessing(opts),
+        );
+        if let Some(unit) = self.units.borrow().get(&key) {
+            self.hits.set(self.hits.get() + 1);
+            return Ok(Rc::clone(unit));
+        }
+        let unit = Rc::new(parse_with_args(self.index, file, &key.1, opts)?);
+        self.units.borrow_mut().insert(key, Rc::clone(&unit));
+        Ok(unit)
+    }
+
+    /// How many times a file was found already parsed.
+    #[must_use]
+    pub fn hits(&self) -> usize {
+        self.hits.get()
+    }
+}
+
+/// Parses `file` with the preprocessor flags of its compile command in `db`, if there's a
+/// database, and the `--extra-arg`s.
+///
+/// # Errors
+/// If clang couldn't parse the file.
+pub fn parse_header<'a>(
+    index: &'a Index,
+    db: Option<&CompilationDatabase>,
+    file: &Path,
+    opts: &Options,
+) -> std::prelude::v1::Result<clang::TranslationUnit<'a>, clang::SourceError> {
+    parse_with_args(index, file, &parse_args(db, file, opts), opts)
+}
+
+/// Parses `file` with the clang arguments `args`, see [`parse_header`].
+fn parse_with_args<'a>(
+    index: &'a Index,
+    file: &Path,
+    args: &[String],
+    opts: &Options,
+) -> std::prelude::v1::Result<clang::TranslationUnit<'a>, clang::SourceError> {
+    index
+        .parser(file)
+        .skip_function_bodies(true)
+        .arguments(args)
+        .keep_going(true)
+        .incomplete(true)
+        .detailed_preprocessing_record(detailed_preprocessing(opts))
+        .parse()
+}
+
+/// The arguments clang parses `file` with, see [`parse_header`].
+fn parse_args(db: Option<&CompilationDatabase>, file: &Path, opts: &Options) -> Vec<String> {
+    let mut args = db
+        .and_then(|db| db.get_compile_commands(file).ok())
+        .and_then(|cc| compile_command(&cc.get_commands(), file, opts))
+        .map(preprocessor_args)
+        .unwrap_or_default();
+    // After the database's `-std`, so it wins
+    if let Some(std) = &opts.c_std {
+        args.push(format!("-std={std}"));
+    }
+    args.extend(opts.extra_args.iter().cloned());
+    args.push("-D__STATIC_INLINE=".to_owned());
+    args.push("-Dinline=".to_owned());
+    args
+}
+
+/// Whether the macro definitions are needed, which only some options read.
+fn detailed_preprocessing(opts: &Options) -> bool {
+    opts.instance_asserts || opts.reexport_constants || opts.version_guard
+}
@@ -1,362 +1,637 @@
-#![feature(try_blocks)]
-#![feature(let_chains)]
 #![warn(clippy::pedantic, clippy::perf)]
-use std::ffi::OsStr;
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::num::NonZeroUsize;
+use std::panic::AssertUnwindSafe;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
-use anyhow::{bail, Context, Error, Result};
-use clang::sonar::{find_functions, find_structs, find_typedefs};
-use clang::{sonar, CompilationDatabase, Index, Type};
-use clang::{Clang, Parser};
-use clap::Parser as ClapParser;
-use convert_case::{Case, Casing};
-use glob::glob;
+use anyhow::{anyhow, bail, Context, Result};
+use clang::{Clang, CompilationDatabase, Index};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser as ClapParser};
+use fxhash::FxHashSet;
+use glob::{glob, Pattern};
+use indicatif::{ProgressBar, ProgressStyle};
 use itertools::{chain, Itertools};
-use lang_c::driver::{parse, Config};
 use rayon::prelude::*;
+use serde::Serialize;
+use sthal_to_cpp::{
+    hal_ll_overlap, libclang_version, log, module_name, parse_file, render_headers, set_verbosity,
+    verbosity, warn_output_collisions, write_output, Converted, Options, OutOfDate, ParseCache,
+    Verbosity, DEFAULT_GLOBS,
+};
 
-#[derive(ClapParser, Debug)] // requires `derive` feature
+#[derive(ClapParser, Debug, Serialize)] // requires `derive` feature
 #[command(term_width = 0)] // Just to make testing across clap features easier
+#[allow(clippy::struct_excessive_bools)] // CLI flags
 struct Args {
-    compiler: PathBuf,
-    input: String,
-    #[arg(default_value = ".")]
+    /// Directory of the `compile_commands.json` the HAL/LL sources are built with. Without one,
+    /// the files are parsed with only the `--extra-arg`s
+    #[arg(short = 'p', long, value_name = "DIR")]
+    compiler: Option<PathBuf>,
+    /// The driver directories to search for HAL/LL files
+    #[arg(required = true, num_args = 1..)]
+    input: Vec<String>,
+    #[arg(required = true)]
     outdir: PathBuf,
+    /// Pattern, relative to each `input`, of the HAL/LL files to convert. Repeatable, replaces
+    /// the default patterns
+    #[arg(long = "glob", value_name = "PATTERN", default_values_t = DEFAULT_GLOBS.map(String::from))]
+    globs: Vec<String>,
+    /// Pattern of files not to convert among those matching `--glob`, e.g. `*_template.c`.
+    /// Repeatable
+    #[arg(long = "exclude", value_name = "GLOB")]
+    excludes: Vec<String>,
+    /// File listing the HAL/LL files to convert, one path per line (`#` starts a comment),
+    /// instead of searching the `input`s with `--glob`. The `input`s are then only the roots of
+    /// `--namespace-from-path`. `--exclude` still applies
+    #[arg(long, value_name = "FILE")]
+    files_from: Option<PathBuf>,
+    /// Convert only the modules of this peripheral, e.g. `uart`. Repeatable, case-insensitive
+    #[arg(long, value_name = "PERIPH")]
+    #[serde(skip)]
+    only: Vec<String>,
+    #[command(flatten)]
+    #[serde(flatten)]
+    options: Options,
+    /// Also write a JSON description of each converted file's classes and wrappers, and of the C
+    /// functions they wrap, to this file. `modules` holds those, `libclang` the libclang version
+    /// they were parsed with
+    #[arg(long, value_name = "PATH.json")]
+    report: Option<PathBuf>,
+    /// Stop at the first file that fails to convert, instead of converting all the others and
+    /// failing at the end. Files clang reported errors for count as failed
+    #[arg(long)]
+    #[serde(skip)]
+    strict: bool,
+    /// Number of files converted in parallel, by default one per CPU. Parsing with clang takes
+    /// most of the memory, so lower this on machines short of RAM. `--jobs 1` converts the files
+    /// one after the other, which is easier to debug
+    #[arg(short, long, visible_alias = "max-threads", value_name = "N")]
+    #[serde(skip)]
+    jobs: Option<NonZeroUsize>,
+    /// Also report why functions were skipped, renamed or made `const` methods
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    #[serde(skip)]
+    verbose: u8,
+    /// Only report errors
+    #[arg(short, long)]
+    #[serde(skip)]
+    quiet: bool,
+    /// TOML file of options, keyed by their long name in snake case like `--print-config`
+    /// prints them, e.g. `status_mode = "bool"`. Options for a single peripheral go in a
+    /// `[peripherals.<periph>]` table. Precedence: command line > peripheral table > config
+    /// file > default
+    #[arg(long, value_name = "FILE.toml")]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+    /// The options of the peripherals with a table in the `config`, by lowercase name. Sorted,
+    /// so `--print-config` prints them in a stable order
+    #[arg(skip)]
+    #[serde(rename = "peripherals")]
+    overrides: BTreeMap<String, Options>,
+    /// Print the effective configuration as TOML, with the peripheral tables, and exit
+    #[arg(long)]
+    #[serde(skip)]
+    print_config: bool,
+    /// How the result of each file is reported on stderr
+    #[arg(long, value_enum, default_value_t, value_name = "FORMAT")]
+    #[serde(skip)]
+    diagnostics: Diagnostics,
+    /// Print the files that would be converted, with their device family, HAL/LL and peripheral,
+    /// and why the others are skipped, without parsing them. Then exit
+    #[arg(long)]
+    #[serde(skip)]
+    list: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Diagnostics {
+    /// `[OK]` and `[SKIP]` lines, the errors and warnings as text
+    #[default]
+    Human,
+    /// Only one JSON object per line and file, with `file`, `status` (`ok` or `error`),
+    /// `message` and `methods_generated`. A file whose conversion panicked is an error too
+    Json,
 }
 
 fn main() -> Result<()> {
-    println!("Hello, world!");
-    let args = Args::parse();
+    let mut args = parse_args()?;
+    let json = args.diagnostics == Diagnostics::Json;
+    if json {
+        // The panics are reported in the failed file's record
+        std::panic::set_hook(Box::new(|_| {}));
+    }
+    set_verbosity(if args.quiet || json {
+        Verbosity::Quiet
+    } else if args.verbose > 0 {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    });
+    args.options.resolve()?;
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs.get())
+            .build_global()?;
+    }
+    args.options.path_roots = args.input.iter().map(PathBuf::from).collect();
+    for options in args.overrides.values_mut() {
+        options.path_roots.clone_from(&args.options.path_roots);
+    }
+    if args.print_config {
+        print!("{}", toml::to_string(&args)?);
+        return Ok(());
+    }
+    let libclang = libclang_version()?;
+    if args.list {
+        log!(Normal, "Using {libclang}");
+        return list_files(&args);
+    }
+    log!(Verbose, "Using {libclang}");
+    let opts = &args.options;
+    if !opts.check && !opts.dry_run {
+        std::fs::create_dir_all(&args.outdir).with_context(|| {
+            format!(
+                "Could not create the output directory {}",
+                args.outdir.display()
+            )
+        })?;
+    }
     // TODO: find the generated handles from Core/
     // We can RAII the init function
-    let files = chain!(
-        glob(&(args.input.clone() + "/*/*hal*.c"))?,
-        glob(&(args.input + "/*/*ll*.h"))?
-    );
-    let clang = Clang::new().expect("Unable to initialize clang");
-    let index = Index::new(&clang, false, false);
-    let db = CompilationDatabase::from_directory(args.compiler)
-        .ok()
-        .context("Could not get db")?;
-    for file in files {
-        let res = parse_file(&index, &db, file, &args.outdir);
+    let (files, mut failed) = input_files(&args)?;
+    let hal_ll_overlap = hal_ll_overlap(files.iter());
+    if opts.single_file.is_none() {
+        warn_output_collisions(files.iter(), &args.outdir, opts);
+    }
+    // After the overlap, which names the classes, is found among all the modules
+    let files = select_peripherals(files, &args.only);
+    let listed_found = Mutex::default();
+    let results = convert_files(&args, &files, &hal_ll_overlap, &listed_found)?;
+    let (mut headers, mut reports, mut out_of_date) = (vec![], vec![], vec![]);
+    for (file, res) in files.iter().zip(results) {
+        print_result(file, &res, args.diagnostics);
         match res {
-            Ok(msg) => eprintln!("[OK] {msg}"),
-            Err(e) => {
-                eprintln!("{e}");
-                // eprintln!("{e}", e = e.backtrace());
+            Ok(converted) if converted.skipped => {}
+            Ok(converted) => {
+                headers.push(converted.header);
+                reports.extend(converted.report);
             }
+            Err(e) => match e.downcast::<OutOfDate>() {
+                Ok(OutOfDate(path)) => out_of_date.push(path),
+                Err(_) => failed += 1,
+            },
+        }
+    }
+    if let Some(single_file) = &opts.single_file {
+        let name = single_file
+            .file_name()
+            .map_or(single_file.as_path(), Path::new);
+        let res = write_output(single_file, &render_headers(&headers, name, opts)?, opts);
+        if let Err(e) = res {
+            out_of_date.push(e.downcast::<OutOfDate>()?.0);
+        }
+    }
+    if let Some(report) = &args.report {
+        let report_json = serde_json::json!({ "libclang": libclang, "modules": reports });
+        std::fs::write(report, serde_json::to_string_pretty(&report_json)?)
+            .with_context(|| format!("Could not write {}", report.display()))?;
+    }
+    if let Some(function_list) = &opts.function_list {
+        let listed_found: FxHashSet<String> = listed_found.into_inner().expect("not poisoned");
+        for missing in function_list.difference(&listed_found).sorted() {
+            log!(
+                Normal,
+                "[WARN] {missing} is listed in --functions-from but wasn't found"
+            );
         }
     }
 
+    log!(Normal, "{} converted, {failed} failed", headers.len());
+    if json && (failed > 0 || !out_of_date.is_empty()) {
+        // The records already say which files failed
+        std::process::exit(1);
+    }
+    check_up_to_date(&out_of_date)?;
+    if failed > 0 {
+        bail!("{failed} files failed to convert");
+    }
     Ok(())
 }
 
-fn parse_file(
-    index: &Index,
-    db: &CompilationDatabase,
-    file: Result<std::path::PathBuf, glob::GlobError>,
-    outdir: &Path,
-) -> Result<String> {
-    let file = file?;
-    println!("{}", file.display());
-
-    let ofname = &file
-        .file_name()
-        .context("Invalid filename")?
-        .to_str()
-        .context("Non-utf-8 filename")?;
-    let ofname = ofname
-        .strip_suffix(".c")
-        .or_else(|| ofname.strip_suffix(".h"))
-        .context("Wrong extension")?;
+impl Args {
+    /// The options `file` is converted with, those of its peripheral's `[peripherals.<periph>]`
+    /// table if the config has one.
+    fn options_for(&self, file: &Path) -> &Options {
+        module_name(file)
+            .ok()
+            .and_then(|name| self.overrides.get(&name.periph_type.to_lowercase()))
+            .unwrap_or(&self.options)
+    }
+}
 
-    let Some((stver, fname)) = ofname.split_once('_') else {
-        bail!("Invalid file name {ofname}")
+/// Parses the command line, filling the options it doesn't set from the `--config` file.
+fn parse_args() -> Result<Args> {
+    let cli = std::env::args_os().collect_vec();
+    let matches = Args::command().get_matches_from(&cli);
+    let args = Args::from_arg_matches(&matches)?;
+    let Some(config) = &args.config else {
+        return Ok(args);
     };
-    let Some((hal_type, periph_type)) = fname.split_once('_') else {
-        bail!("Invalid file name {fname}")
+    let mut table: toml::Table = std::fs::read_to_string(config)
+        .with_context(|| format!("Could not read {}", config.display()))?
+        .parse()
+        .with_context(|| format!("Could not parse {}", config.display()))?;
+    let peripherals = match table.remove("peripherals") {
+        None => toml::Table::new(),
+        Some(toml::Value::Table(peripherals)) => peripherals,
+        Some(_) => bail!("`peripherals` must be a table of peripheral names to options"),
+    };
+    // The config's values go before the command line's, which doesn't repeat them
+    let reparse = |table: &toml::Table| -> Result<Args> {
+        let config_args = config_args(table, &matches)
+            .with_context(|| format!("Invalid config {}", config.display()))?;
+        let args = chain!(&cli[..1], &config_args, &cli[1..]);
+        Args::try_parse_from(args).with_context(|| format!("Invalid config {}", config.display()))
     };
-    if periph_type.ends_with("_ex") {
-        bail!("Skipping extension module, already processed in main module")
+    let mut args = reparse(&table)?;
+    for (periph, options) in peripherals {
+        let toml::Value::Table(options) = options else {
+            bail!("`peripherals.{periph}` must be a table of options");
+        };
+        let mut table = table.clone();
+        table.extend(options);
+        let mut options = reparse(&table)?.options;
+        options.resolve()?;
+        args.overrides.insert(periph.to_lowercase(), options);
     }
-    if !["hal", "ll"].contains(&hal_type) {
-        bail!("Invalid hal_type {fname}");
+    Ok(args)
+}
+
+/// The command line arguments setting the options in the config `table` that aren't set in
+/// `matches`.
+fn config_args(table: &toml::Table, matches: &ArgMatches) -> Result<Vec<std::ffi::OsString>> {
+    let command = Args::command();
+    let mut args = vec![];
+    for (key, value) in table {
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == key && !["help", "config"].contains(&key.as_str()))
+        else {
+            bail!("Unknown option `{key}`");
+        };
+        // The positional `input` and `outdir` are always on the command line
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        if matches.value_source(key) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let values = match value {
+            toml::Value::Array(values) => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        for value in values {
+            match value {
+                // Flags defaulting to true take a value, the others are only present or not
+                toml::Value::Boolean(value) if matches!(arg.get_action(), ArgAction::Set) => {
+                    args.push(format!("--{long}={value}").into());
+                }
+                toml::Value::Boolean(true) => args.push(format!("--{long}").into()),
+                toml::Value::Boolean(false) => {}
+                toml::Value::String(value) => args.push(format!("--{long}={value}").into()),
+                toml::Value::Integer(value) => args.push(format!("--{long}={value}").into()),
+                _ => bail!("`{key}` must be a boolean, string or integer, or an array of them"),
+            }
+        }
     }
+    Ok(args)
+}
 
-    let hdr = parse_header(index, db, &file).context("Could not parse the file")?;
-    // dbg!(hdr.get_diagnostics());
-    let functions = find_functions(hdr.get_entity().get_children()).collect_vec();
+/// Converts the `files` in parallel, see `parse_file`. Under `--strict` the first failure is
+/// returned, otherwise each file's result in order.
+fn convert_files(
+    args: &Args,
+    files: &[PathBuf],
+    hal_ll_overlap: &FxHashSet<String>,
+    listed_found: &Mutex<FxHashSet<String>>,
+) -> Result<Vec<Result<Converted>>> {
+    let clang = SharedClang(Clang::new().expect("Unable to initialize clang"));
+    let has_db = args
+        .compiler
+        .as_ref()
+        .is_some_and(|dir| CompilationDatabase::from_directory(dir).is_ok());
+    if !has_db {
+        log!(
+            Normal,
+            "[WARN] No compilation database found, parsing with only --extra-arg, include paths may be incomplete"
+        );
+    }
+    let cache_hits = AtomicUsize::new(0);
+    let progress = progress_bar(files.len() as u64);
+    // Each worker parses with its own `Index` and compilation database, neither can be shared
+    // between threads. Files with the same canonical path go to the same worker, which parses
+    // them once, and their units are dropped once it's done with them. The results are reported
+    // in glob order once all are done
+    let groups = files
+        .iter()
+        .enumerate()
+        .into_group_map_by(|(_, file)| file.canonicalize().unwrap_or_else(|_| (*file).clone()))
+        .into_values()
+        .collect_vec();
+    let convert = |cache: &ParseCache, file: &PathBuf| {
+        // A bug converting one file fails only that file
+        let converted = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            parse_file(
+                cache,
+                file,
+                &args.outdir,
+                hal_ll_overlap,
+                listed_found,
+                args.options_for(file),
+            )
+        }))
+        .unwrap_or_else(|panic| {
+            let reason = panic
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown cause");
+            Err(anyhow!("Converting {} panicked: {reason}", file.display()))
+        });
+        progress.inc(1);
+        let converted = converted?;
+        if args.strict && !converted.parse_errors.is_empty() {
+            bail!("{}", converted.message);
+        }
+        Ok(converted)
+    };
+    let next_group = AtomicUsize::new(0);
+    let worker = |_| {
+        let SharedClang(clang) = &clang;
+        let index = Index::new(clang, false, false);
+        let db = args.compiler.as_ref().filter(|_| has_db).map(|dir| {
+            CompilationDatabase::from_directory(dir).expect("loaded on the main thread")
+        });
+        let mut converted = vec![];
+        while let Some(group) = groups.get(next_group.fetch_add(1, Ordering::Relaxed)) {
+            let cache = ParseCache::new(&index, db.as_ref());
+            for &(i, file) in group {
+                let res = convert(&cache, file);
+                // Under --strict the first failure stops the other workers
+                if args.strict && res.is_err() {
+                    next_group.store(groups.len(), Ordering::Relaxed);
+                }
+                converted.push((i, if args.strict { Ok(res?) } else { res }));
+            }
+            cache_hits.fetch_add(cache.hits(), Ordering::Relaxed);
+        }
+        Ok(converted)
+    };
+    let workers = rayon::current_num_threads().min(groups.len());
+    let results = (0..workers)
+        .into_par_iter()
+        .map(worker)
+        .collect::<Result<Vec<_>>>();
+    // The summary is printed once all files are converted
+    progress.finish_and_clear();
+    let results = results?
+        .into_iter()
+        .flatten()
+        .sorted_by_key(|&(i, _)| i)
+        .map(|(_, res)| res)
+        .collect_vec();
+    let cache_hits = cache_hits.into_inner();
+    if cache_hits > 0 {
+        log!(
+            Verbose,
+            "{cache_hits} files were found already parsed and not parsed again"
+        );
+    }
+    Ok(results)
+}
+
+/// Reports the result of converting `file` like `--diagnostics` selects. Out of date files are
+/// listed together by `check_up_to_date` otherwise.
+fn print_result(file: &Path, res: &Result<Converted>, diagnostics: Diagnostics) {
+    match (res, diagnostics) {
+        (Ok(converted), Diagnostics::Json) => print_diagnostic(
+            file,
+            "ok",
+            &converted.message,
+            converted.header.method_count(),
+        ),
+        (Err(e), Diagnostics::Json) => print_diagnostic(file, "error", &format!("{e:#}"), 0),
+        (Ok(converted), _) if converted.skipped => log!(Normal, "[SKIP] {}", converted.message),
+        (Ok(converted), _) => log!(Normal, "[OK] {}", converted.message),
+        (Err(e), _) if e.is::<OutOfDate>() => {}
+        (Err(e), _) => {
+            eprintln!("{e}");
+            // eprintln!("{e}", e = e.backtrace());
+        }
+    }
+}
 
-    let handle_types = find_handle_types(hal_type, &hdr, periph_type, &functions);
+/// Prints the `--diagnostics json` record of `file` to stderr.
+fn print_diagnostic(file: &Path, status: &str, message: &str, methods_generated: usize) {
+    let record = serde_json::json!({
+        "file": file,
+        "status": status,
+        "message": message,
+        "methods_generated": methods_generated,
+    });
+    eprintln!("{record}");
+}
 
-    let inc_name = if hal_type == "hal" {
-        &format!("{stver}_hal")
-    } else {
-        ofname
-    };
-    let gen_code = generate_code(handle_types, inc_name, periph_type, &functions, hal_type)?;
+/// A bar counting the converted files on stderr. Hidden under `--quiet` or when the output
+/// isn't a terminal, e.g. in scripts.
+fn progress_bar(len: u64) -> ProgressBar {
+    if verbosity() == Verbosity::Quiet || !std::io::stdout().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    ProgressBar::new(len).with_style(
+        ProgressStyle::with_template("{bar:40} {pos}/{len} files ({elapsed})")
+            .expect("valid template"),
+    )
+}
 
-    let new_file = outdir.join(fname).with_extension("hpp");
-    {
-        let file = File::create(&new_file).context("Could not create new file")?;
-        let mut file = BufWriter::new(file);
-        file.write_all(gen_code.as_bytes())?;
+/// The files matching `--glob` in any `input` that follow the HAL/LL naming scheme and aren't
+/// `--exclude`d, and the number of glob matches that couldn't be read. Under `--strict` those
+/// are an error.
+fn input_files(args: &Args) -> Result<(Vec<PathBuf>, usize)> {
+    let excludes = exclude_patterns(args)?;
+    let mut files = vec![];
+    let mut failed = 0;
+    let mut num_excluded = 0;
+    for file in candidate_files(args, &mut failed)? {
+        if excludes.iter().any(|exclude| exclude.matches_path(&file)) {
+            num_excluded += 1;
+            continue;
+        }
+        // Not a failure, e.g. `_ex` modules are converted with their base module
+        if let Err(e) = module_name(&file) {
+            log!(Normal, "[SKIP] {}: {e}", file.display());
+            continue;
+        }
+        files.push(file);
     }
+    if num_excluded > 0 {
+        log!(Normal, "Excluded {num_excluded} files");
+    }
+    Ok((files, failed))
+}
 
-    Ok(format!(
-        "{} converted to {}",
-        file.display(),
-        new_file.display()
-    ))
+/// The `--exclude` patterns.
+fn exclude_patterns(args: &Args) -> Result<Vec<Pattern>> {
+    Ok(args
+        .excludes
+        .iter()
+        .map(|exclude| Pattern::new(exclude))
+        .collect::<Result<Vec<_>, _>>()?)
 }
 
-fn generate_code(
-    handle_types: Vec<(String, Type)>,
-    inc_name: &str,
-    periph_type: &str,
-    functions: &[sonar::Declaration],
-    hal_type: &str,
-) -> Result<String, Error> {
-    use std::fmt::Write;
-    let mut code = String::new();
-    writeln!(code, "#pragma once")?;
-    writeln!(code, "#include \"{inc_name}.h\"")?;
-    // if hal_type == "hal" {
-    //     writeln!(code, "#include \"ll_{periph_type}.hpp\"")?;
-    // }
-    writeln!(code, "namespace {hal_type} {{")?;
-    if handle_types.is_empty() {
-        let cname = periph_type.to_case(Case::UpperCamel);
-        writeln!(code, "namespace {cname} {{")?;
-        code.extend(static_functions(functions, hal_type, periph_type));
-        writeln!(code, "}};")?;
+/// The files listed by `--files-from`, or matching `--glob` in the `input`s, before
+/// `--exclude` applies.
+fn candidate_files(args: &Args, failed: &mut usize) -> Result<Vec<PathBuf>> {
+    if let Some(files_from) = &args.files_from {
+        listed_files(files_from)
     } else {
-        for (handle_typename, _) in handle_types {
-            let Some((cname, _)) = handle_typename.rsplit_once('_') else {
-                eprintln!("Weird handle type {handle_typename}");
-                continue;
-            };
-            let cname = cname.to_case(Case::UpperCamel);
-            // TODO: version that extends the struct rather than storing a handle
-            writeln!(code, "class {cname} {{")?;
-            writeln!(code, "public:")?;
-            writeln!(code, "{handle_typename} {periph_type};")?;
-            writeln!(
-                code,
-                "{cname}({handle_typename} {periph_type}) : {periph_type}({periph_type}) {{}}"
-            )?;
-            // if hal_type == "hal" {
-            //     writeln!(code, "template <bool dummy=true>")?;
-            //     writeln!(
-            //         code,
-            //         "ll::{cname} ll() {{ return ll::{cname}{{ {periph_type}->Instance }}; }}"
-            //     )?;
-            // }
-            // TODO: add virtual functions for callbacks
-            code.extend(handle_functions(
-                functions,
-                &handle_typename,
-                hal_type,
-                periph_type,
-            ));
-            writeln!(code, "}};")?;
-        }
+        globbed_files(args, failed)
     }
-    writeln!(code, "}};")?;
-    Ok(code)
 }
 
-fn find_handle_types<'a>(
-    hal_type: &str,
-    hdr: &'a clang::TranslationUnit<'a>,
-    periph_type: &str,
-    functions: &'a [sonar::Declaration<'a>],
-) -> Vec<(String, Type<'a>)> {
-    let handle_types = if hal_type == "hal" {
-        find_structs(hdr.get_entity().get_children())
-            .map(|decl| (decl.name, decl.entity.get_type().expect("Known type")))
-            .filter(|(decl, _)| decl.ends_with("_HandleTypeDef"))
-            .filter(|(decl, _)| decl.to_lowercase().contains(&periph_type.to_lowercase()))
-            .filter(|(decl, _)| !decl.contains("const"))
-            .map(|(decl, ty)| (decl + " *", ty))
-            .collect_vec()
-    } else if hal_type == "ll" {
-        functions
-            .iter()
-            .filter(|decl| decl.name.contains(&periph_type.to_uppercase()))
-            .filter_map(|decl| try {
-                let ty = decl
-                    .entity
-                    .get_arguments()
-                    .expect("known function")
-                    .first()?
-                    .get_type()
-                    .expect("args have types");
-                (ty.get_display_name(), ty)
-            })
-            .filter(|(name, _)| name.contains("_TypeDef"))
-            // .filter(|decl| decl.contains(&periph_type.to_uppercase()))
-            .filter(|(decl, _)| !decl.contains("const"))
-            .filter(|(decl, _)| decl.to_lowercase().contains(&periph_type.to_lowercase()))
-            .fold(vec![], |mut types, (name, ty)| {
-                if types.iter().all(|(n, _)| n != &name) {
-                    types.push((name, ty));
+/// Prints a table of the files `--list` finds: the module each one is, or why it's skipped.
+fn list_files(args: &Args) -> Result<()> {
+    let excludes = exclude_patterns(args)?;
+    let mut rows = vec![["VERSION", "TYPE", "PERIPHERAL", "FILE", "STATUS"].map(String::from)];
+    for file in candidate_files(args, &mut 0)? {
+        let path = file.display().to_string();
+        let row = if excludes.iter().any(|exclude| exclude.matches_path(&file)) {
+            ["", "", "", &path, "excluded"].map(String::from)
+        } else {
+            match module_name(&file) {
+                Ok(name) => {
+                    let selected = args.only.is_empty()
+                        || args
+                            .only
+                            .iter()
+                            .any(|periph| periph.eq_ignore_ascii_case(&name.periph_type));
+                    let status = if selected { "" } else { "not in --only" };
+                    [
+                        name.stver,
+                        name.hal_type,
+                        name.periph_type,
+                        path,
+                        status.to_owned(),
+                    ]
                 }
-                types
-            })
-    } else {
-        unreachable!("Unknown hal_type {hal_type}");
-    };
-    handle_types
+                Err(e) => ["", "", "", &path, &format!("skipped: {e}")].map(String::from),
+            }
+        };
+        rows.push(row);
+    }
+    let widths = (0..rows[0].len())
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect_vec();
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{cell:<width$}"))
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+    Ok(())
 }
 
-fn handle_functions(
-    functions: &[sonar::Declaration],
-    handle_type: &str,
-    hal_type: &str,
-    periph_type: &str,
-) -> Vec<String> {
-    let is_ll = hal_type == "ll";
-    let handle_type = handle_type.strip_prefix("__").unwrap_or(handle_type);
-    let periph_up = &periph_type.to_uppercase();
-    functions
+/// The files matching `--glob` in the `input`s. Unreadable paths are reported and counted in
+/// `failed`, or fail under `--strict`.
+fn globbed_files(args: &Args, failed: &mut usize) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let patterns = args
+        .input
         .iter()
-        .filter(|decl| {
-            (!is_ll && decl.name.starts_with("HAL_")) || (is_ll && decl.name.starts_with("LL_"))
-        })
-        .filter(|decl| !decl.name.ends_with("IRQHandler") && !decl.name.ends_with("Callback"))
-        .rev()
-        .map(|decl| {
-            let code: Option<String> = try {
-                let ret_type = decl
-                    .entity
-                    .get_result_type()
-                    .expect("known function")
-                    .get_display_name();
-                let oname = &decl.name;
-                let name = oname.split_once('_')?.1;
-                let name = name.replace(&(periph_up.clone() + "_"), "");
-                let name = &name.to_case(Case::Camel);
-                let name = name.strip_prefix(periph_type).unwrap_or(name);
-                let name = name.strip_prefix("_").unwrap_or(name);
-                let mut args = decl.entity.get_arguments().expect("known function");
-                if args.is_empty() {
-                //     if oname.contains(periph_up) {
-                // return format!(
-                //     "\tstatic inline {ret_type} {name}() {{ return {oname}(); }}\n"
-                // )
-                //     }
-                    return String::new();
+        .cartesian_product(&args.globs)
+        .map(|(input, pattern)| Path::new(input).join(pattern));
+    for pattern in patterns {
+        for file in glob(&pattern.to_string_lossy())? {
+            match file {
+                Ok(file) => files.push(file),
+                Err(e) if args.strict => return Err(e.into()),
+                Err(e) if args.diagnostics == Diagnostics::Json => {
+                    print_diagnostic(e.path(), "error", &e.error().to_string(), 0);
+                    *failed += 1;
                 }
-                let first = args[0];
-                let arg_type = &first
-                    .get_type()
-                    .expect("args have types")
-                    .get_display_name();
-                let (prefix, handle) = if arg_type
-                    .starts_with(handle_type) || arg_type.strip_prefix("const ").map_or(false, |arg_type| arg_type.starts_with(handle_type))
-                {
-                    args.remove(0);
-                    ("", vec![format!("this->{periph_type}")])
+                Err(e) => {
+                    eprintln!("{e}");
+                    *failed += 1;
                 }
-                // else if oname.contains(periph_up) {
-                //     ("static ", vec![])
-                // }
-                else {
-                    return String::new();
-                };
-                let call_args = chain!(
-                    handle,
-                    args.iter()
-                        .map(|arg| arg.get_name().expect("args have names"))
-                )
-                .join(", ");
-                let args = args
-                    .into_iter()
-                    .map(|arg| arg.get_pretty_printer().print())
-                    .join(", ");
-
-                format!(
-                    "\t{prefix}inline {ret_type} {name}({args}) {{ return {oname}({call_args}); }}\n"
-                )
-            };
-            code.unwrap_or_default()
-        })
-        .collect_vec()
+            }
+        }
+    }
+    Ok(files)
 }
 
-fn static_functions(functions: &[sonar::Declaration], hal_type: &str, periph: &str) -> Vec<String> {
-    let is_ll = hal_type == "ll";
-    let periph_up = &periph.to_uppercase();
-    functions
-        .iter()
-        .filter(|decl| {
-            (!is_ll && decl.name.starts_with("HAL_")) || (is_ll && decl.name.starts_with("LL_"))
-        })
-        .filter(|decl| !decl.name.ends_with("IRQHandler") && !decl.name.ends_with("Callback"))
-        .rev()
-        .map(|decl| {
-            let code: Option<String> = try {
-                let ret_type = decl
-                    .entity
-                    .get_result_type()
-                    .expect("known function")
-                    .get_display_name();
-                // TODO: convert StatusTypeDef into bool
-                let oname = &decl.name;
-                if !oname.contains(periph_up) {
-                    return String::new();
-                }
-                let name = oname.split_once('_')?.1;
-                let name = name.replace(&(periph_up.clone() + "_"), "");
-                let name = &name.to_case(Case::Camel);
-                let name = name.strip_prefix(periph).unwrap_or(name);
-                let name = name.strip_prefix("_").unwrap_or(name);
-                let args = decl.entity.get_arguments().expect("known function");
-                let call_args = args
-                    .iter()
-                    .map(|arg| arg.get_name().expect("args have names"))
-                    .join(", ");
-                let args = args
-                    .into_iter()
-                    .map(|arg| arg.get_pretty_printer().print())
-                    .join(", ");
+/// The files listed in the `--files-from` file `list`, skipping blank lines and comments.
+fn listed_files(list: &Path) -> Result<Vec<PathBuf>> {
+    let contents = std::fs::read_to_string(list)
+        .with_context(|| format!("Could not read {}", list.display()))?;
+    Ok(contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
 
-                format!(
-                    "\tstatic inline {ret_type} {name}({args}) {{ return {oname}({call_args}); }}\n"
-                )
-            };
-            code.unwrap_or_default()
-        })
-        .collect_vec()
+/// Fails under `--check` if any of the outputs were `out_of_date`, naming the first few.
+fn check_up_to_date(out_of_date: &[PathBuf]) -> Result<()> {
+    const SHOWN: usize = 5;
+    if out_of_date.is_empty() {
+        return Ok(());
+    }
+    for path in out_of_date.iter().take(SHOWN) {
+        eprintln!("{} is out of date", path.display());
+    }
+    if out_of_date.len() > SHOWN {
+        eprintln!("...");
+    }
+    bail!(
+        "{} generated files are out of date, regenerate them",
+        out_of_date.len()
+    );
 }
 
-fn parse_header<'a>(
-    index: &'a Index,
-    db: &CompilationDatabase,
-    file: &Path,
-) -> std::prelude::v1::Result<clang::TranslationUnit<'a>, clang::SourceError> {
-    let mut args = db
-        .get_compile_commands(file)
-        .ok()
-        .and_then(|cc| {
-            cc.get_commands()
-                .first()
-                .map(clang::CompileCommand::get_arguments)
+/// The `files` of the `--only` peripherals, all of them without the option.
+fn select_peripherals(files: Vec<PathBuf>, only: &[String]) -> Vec<PathBuf> {
+    if only.is_empty() {
+        return files;
+    }
+    let (selected, skipped): (Vec<_>, Vec<_>) = files.into_iter().partition(|file| {
+        module_name(file).is_ok_and(|name| {
+            only.iter()
+                .any(|periph| periph.eq_ignore_ascii_case(&name.periph_type))
         })
-        .unwrap_or_default();
-    args.retain(|arg| arg.starts_with("-D") || arg.starts_with("-I"));
-    args.push("-D__STATIC_INLINE=".to_owned());
-    args.push("-Dinline=".to_owned());
-    index
-        .parser(file)
-        .skip_function_bodies(true)
-        .arguments(&args)
-        .keep_going(true)
-        .incomplete(true)
-        .parse()
+    });
+    let periphs = |files: &[PathBuf]| {
+        files
+            .iter()
+            .filter_map(|file| Some(module_name(file).ok()?.periph_type))
+            .unique()
+            .join(", ")
+    };
+    log!(Normal, "Selected {}", periphs(&selected));
+    log!(Normal, "Skipped {}", periphs(&skipped));
+    selected
 }
+
+/// Lets the rayon workers create their own `Index` from the process-wide `Clang`. `Clang` is
+/// only a token that libclang is loaded, and libclang allows concurrent use of separate
+/// indices, which is all the workers do with it.
+struct SharedClang(Clang);
+
+// SAFETY: see above, the workers only call `Index::new` with it
+unsafe impl Sync for SharedClang {}
@@ -0,0 +1,27 @@
+use sthal_to_cpp::{write_output, Options};
+
+#[test]
+fn only_the_generated_region_is_replaced() {
+    let dir = std::env::temp_dir().join("sthal_to_cpp_append_mode");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("hal_uart.hpp");
+    let options = Options {
+        append_mode: true,
+        ..Options::default()
+    };
+
+    std::fs::write(&path, "#include \"mine.h\"\n").unwrap();
+    write_output(&path, "old\n", &options).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "// BEGIN sthal-generated\nold\n// END sthal-generated\n"
+    );
+
+    let edited = "// Mine\n// BEGIN sthal-generated\nold\n// END sthal-generated\nvoid mine();\n";
+    std::fs::write(&path, edited).unwrap();
+    write_output(&path, "new\n", &options).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "// Mine\n// BEGIN sthal-generated\nnew\n// END sthal-generated\nvoid mine();\n"
+    );
+}
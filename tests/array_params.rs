@@ -0,0 +1,27 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn array_parameters_are_declared_and_forwarded() {
+    let options = Options {
+        out_params: true,
+        refs: true,
+        ..Options::default()
+    };
+    let code = common::convert("ll_arr", &options);
+    // The extent follows the name
+    assert!(code.contains("load(const uint8_t data[16])"), "{code}");
+    assert!(code.contains("LL_ARR_Load(data)"), "{code}");
+    assert!(code.contains("fill(uint8_t arg0[4])"), "{code}");
+    assert!(code.contains("LL_ARR_Fill(arg0)"), "{code}");
+    assert!(code.contains("matrix(uint32_t m[2][3])"), "{code}");
+    assert!(code.contains("LL_ARR_Matrix(m)"), "{code}");
+    assert!(!code.contains("[16] data"), "{code}");
+    assert!(!code.contains("[4] arg0"), "{code}");
+    assert!(
+        code.contains("send(const uint8_t *pData, uint16_t Size)"),
+        "{code}"
+    );
+    assert!(code.contains("LL_ARR_Send(pData, Size)"), "{code}");
+}
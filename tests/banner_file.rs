@@ -0,0 +1,50 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+const BANNER: &str = "/*\n *   Copyright (c) ACME\n *\tAll rights reserved.  \n */";
+
+/// Options reading the `BANNER` from a file, as `--banner-file` does.
+fn options(split_impl: bool) -> Options {
+    let dir = common::outdir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let banner_file = dir.join("LICENSE_HEADER");
+    std::fs::write(&banner_file, BANNER).unwrap();
+    let mut options = Options {
+        banner_file: Some(banner_file),
+        split_impl,
+        ..Options::default()
+    };
+    options.resolve().unwrap();
+    options
+}
+
+#[test]
+fn banner_comes_first_verbatim() {
+    let code = common::convert("hal_uart", &options(false));
+    assert!(
+        code.starts_with(&format!("{BANNER}\n#pragma once\n")),
+        "{code}"
+    );
+}
+
+#[test]
+fn banner_heads_the_split_source_too() {
+    let outdir = common::outdir();
+    common::convert_to("ll_refs", &outdir, &options(true)).unwrap();
+    let source = std::fs::read_to_string(outdir.join("ll_refs.cpp")).unwrap();
+    assert!(
+        source.starts_with(&format!("{BANNER}\n#include \"ll_refs.hpp\"\n")),
+        "{source}"
+    );
+}
+
+#[test]
+fn missing_banner_file_is_an_error() {
+    let mut options = Options {
+        banner_file: Some(common::outdir().join("LICENSE_HEADER")),
+        ..Options::default()
+    };
+    let err = options.resolve().unwrap_err();
+    assert!(format!("{err:#}").contains("Could not read"), "{err:#}");
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn bit_field_setters_take_the_declared_type() {
+    let options = Options {
+        init_builder: true,
+        ..Options::default()
+    };
+    let code = common::convert("hal_bits", &options);
+    assert!(
+        code.contains("BitsInit &mode(uint32_t value) { init.Mode = value; return *this; }"),
+        "{code}"
+    );
+    assert!(code.contains("BitsInit &enable(uint32_t value)"), "{code}");
+    assert!(
+        code.contains("BitsInit &prescaler(uint32_t value)"),
+        "{code}"
+    );
+    assert!(!code.contains(": 3"), "{code}");
+    // The width is documented instead
+    let lines = code.lines().collect::<Vec<_>>();
+    let doc = lines
+        .iter()
+        .position(|line| line.trim() == "/** `Mode` is 3 bits wide, higher bits are dropped */")
+        .expect(&code);
+    assert!(lines[doc + 1].contains("BitsInit &mode("), "{code}");
+    assert!(code.contains("/** `Enable` is 1 bit wide"), "{code}");
+    assert!(!code.contains("`Prescaler` is"), "{code}");
+}
@@ -0,0 +1,21 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn files_are_parsed_with_the_given_standard() {
+    let options = Options {
+        c_std: Some("c99".to_owned()),
+        ..Options::default()
+    };
+    let code = common::convert("ll_std", &options);
+    assert!(code.contains("enableC99()"), "{code}");
+
+    let options = Options {
+        c_std: Some("c11".to_owned()),
+        ..Options::default()
+    };
+    let code = common::convert("ll_std", &options);
+    assert!(code.contains("enable()"), "{code}");
+    assert!(!code.contains("enableC99()"), "{code}");
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::{Options, StatusMode};
+
+fn convert(status_mode: StatusMode) -> String {
+    let options = Options {
+        chain: true,
+        status_mode,
+        ..Options::default()
+    };
+    common::convert("hal_uart", &options)
+}
+
+#[test]
+fn status_methods_store_the_status_and_return_the_object() {
+    let code = convert(StatusMode::Stored);
+    let transmit = method(&code, "transmit");
+    assert!(transmit.contains("Uart & transmit("), "{transmit}");
+    assert!(
+        transmit.contains(
+            "m_last_status = HAL_UART_Transmit(this->uart, pData, Size, Timeout); return *this;"
+        ),
+        "{transmit}"
+    );
+    assert!(
+        method(&code, "deInit").contains("Uart & deInit()"),
+        "{code}"
+    );
+    // Other results are returned as before
+    assert!(
+        method(&code, "getError").contains("uint32_t getError() const"),
+        "{code}"
+    );
+    assert!(
+        code.contains("HAL_StatusTypeDef last_status() const { return m_last_status; }"),
+        "{code}"
+    );
+    assert!(
+        code.contains("HAL_StatusTypeDef m_last_status = HAL_OK;"),
+        "{code}"
+    );
+}
+
+#[test]
+fn status_methods_throw_and_return_the_object() {
+    let code = convert(StatusMode::Throw);
+    let receive = method(&code, "receive");
+    assert!(receive.contains("Uart & receive("), "{receive}");
+    assert!(
+        receive.contains("status != HAL_OK) { throw ::hal::Error{status}; } return *this;"),
+        "{receive}"
+    );
+    assert!(!code.contains("last_status"), "{code}");
+}
+
+#[test]
+fn chain_needs_a_separate_status() {
+    let mut options = Options {
+        chain: true,
+        status_mode: StatusMode::Bool,
+        ..Options::default()
+    };
+    assert!(options.resolve().is_err());
+    let mut options = Options {
+        status_mode: StatusMode::Stored,
+        ..Options::default()
+    };
+    assert!(options.resolve().is_err());
+}
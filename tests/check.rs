@@ -0,0 +1,21 @@
+mod common;
+
+use sthal_to_cpp::{Options, OutOfDate};
+
+#[test]
+fn check_fails_for_stale_outputs_without_writing() {
+    let outdir = common::outdir();
+    let output = outdir.join("ll_foo.hpp");
+    common::convert_to("ll_foo", &outdir, &Options::default()).unwrap();
+    let check = Options {
+        check: true,
+        ..Options::default()
+    };
+    common::convert_to("ll_foo", &outdir, &check).unwrap();
+
+    std::fs::write(&output, "// stale\n").unwrap();
+    let err = common::convert_to("ll_foo", &outdir, &check).unwrap_err();
+    let OutOfDate(path) = err.downcast_ref::<OutOfDate>().unwrap();
+    assert_eq!(path, &output);
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "// stale\n");
+}
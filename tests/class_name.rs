@@ -0,0 +1,21 @@
+use sthal_to_cpp::class_name;
+
+#[test]
+fn hal_handle() {
+    assert_eq!(class_name("UART_HandleTypeDef *").unwrap(), "Uart");
+    assert_eq!(class_name("__UART_HandleTypeDef *").unwrap(), "Uart");
+    assert_eq!(class_name("struct __DMA_HandleTypeDef *").unwrap(), "Dma");
+}
+
+#[test]
+fn ll_register_block() {
+    assert_eq!(class_name("TIM_TypeDef *").unwrap(), "Tim");
+    assert_eq!(class_name("USB_OTG_GlobalTypeDef *").unwrap(), "UsbOtg");
+}
+
+#[test]
+fn no_underscore() {
+    assert_eq!(class_name("TIMTypeDef *").unwrap(), "Tim");
+    assert_eq!(class_name("Handle *").unwrap(), "Handle");
+    assert_eq!(class_name("_TypeDef *"), None);
+}
@@ -0,0 +1,110 @@
+//! Converting the headers in `tests/fixtures`, shared by the tests.
+#![allow(dead_code)] // Each test uses only some of these
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use clang::{Clang, CompilationDatabase, Index};
+use fxhash::FxHashSet;
+use sthal_to_cpp::{convert_header, parse_file, Options, ParseCache};
+
+/// libclang can only be initialized once at a time in a process
+static CLANG: Mutex<()> = Mutex::new(());
+
+/// The fixture of `module`, e.g. `hal_uart` -> `tests/fixtures/Inc/stm32f4xx_hal_uart.h`.
+pub fn fixture(module: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/Inc")
+        .join(format!("stm32f4xx_{module}.h"))
+}
+
+/// A new empty output directory.
+pub fn outdir() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("sthal_to_cpp_test_{}_{n}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Converts the fixture of `module` into `outdir`, returning what was converted where.
+pub fn convert_to(module: &str, outdir: &Path, opts: &Options) -> anyhow::Result<String> {
+    convert_file(&fixture(module), outdir, opts)
+}
+
+/// Runs `f` with a clang index and the fixtures' compilation database.
+pub fn with_clang<T>(f: impl FnOnce(&Index, &CompilationDatabase) -> T) -> T {
+    let _clang = CLANG.lock().unwrap_or_else(PoisonError::into_inner);
+    let clang = Clang::new().unwrap();
+    let index = Index::new(&clang, false, false);
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let db = CompilationDatabase::from_directory(fixtures).unwrap();
+    f(&index, &db)
+}
+
+/// Converts `file` into `outdir`, returning what was converted where.
+pub fn convert_file(file: &Path, outdir: &Path, opts: &Options) -> anyhow::Result<String> {
+    with_clang(|index, db| convert_header(index, Some(db), file, outdir, opts))
+}
+
+/// Converts `file` into `outdir` like the command line does when the peripherals
+/// `hal_ll_overlap` have both a HAL and an LL module among the files it converts. The
+/// `--functions-from` functions found are added to `listed_found`.
+pub fn convert_among(
+    file: &Path,
+    outdir: &Path,
+    hal_ll_overlap: &FxHashSet<String>,
+    listed_found: &Mutex<FxHashSet<String>>,
+    opts: &Options,
+) -> anyhow::Result<String> {
+    with_clang(|index, db| {
+        let cache = ParseCache::new(index, Some(db));
+        let converted = parse_file(&cache, file, outdir, hal_ll_overlap, listed_found, opts)?;
+        Ok(converted.message)
+    })
+}
+
+/// The header generated from the fixture of `module`.
+pub fn convert(module: &str, opts: &Options) -> String {
+    let outdir = outdir();
+    convert_to(module, &outdir, opts).unwrap();
+    std::fs::read_to_string(outdir.join(format!("{module}.hpp"))).unwrap()
+}
+
+/// The line of `code` declaring or defining the method `name`.
+pub fn method<'a>(code: &'a str, name: &str) -> &'a str {
+    let call = format!(" {name}(");
+    code.lines()
+        .find(|line| line.contains(&call))
+        .unwrap_or_else(|| panic!("no {name} in {code}"))
+}
+
+/// Checks that the `header` generated from the fixture of `module` compiles as C++17, if there's
+/// a `c++` compiler to check it with. HAL headers include the family's `stm32f4xx_hal.h`, which
+/// stands in for the fixture next to them.
+pub fn assert_compiles(header: &Path, module: &str) {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/Inc");
+    let family_header = header.with_file_name("stm32f4xx_hal.h");
+    std::fs::write(
+        &family_header,
+        format!("#include \"stm32f4xx_{module}.h\"\n"),
+    )
+    .unwrap();
+    let output = std::process::Command::new("c++")
+        .args(["-std=c++17", "-fsyntax-only", "-x", "c++", "-I"])
+        .arg(fixtures)
+        .arg(header)
+        .output();
+    std::fs::remove_file(family_header).unwrap();
+    let Ok(output) = output else {
+        eprintln!("No c++ compiler, not compiling {}", header.display());
+        return;
+    };
+    assert!(
+        output.status.success(),
+        "{} doesn't compile:\n{}",
+        header.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+/// The `--print-config` output of the command line `args`.
+fn print_config(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_sthal_to_cpp"))
+        .args(["in", "out", "--print-config"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn config_arg(config: &Path) -> String {
+    format!("--config={}", config.display())
+}
+
+#[test]
+fn printed_config_reads_back_the_same() {
+    let dir = common::outdir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    std::fs::write(
+        &config,
+        "status_mode = \"bool\"\nstrip_elaborated = false\ntrace = true\nrefs = false\n\n[peripherals.uart]\nrefs = true\ndefault_args = [\"Timeout=HAL_MAX_DELAY\"]\n",
+    )
+    .unwrap();
+    let printed = print_config(&[&config_arg(&config)]);
+    assert!(printed.contains("strip_elaborated = false"), "{printed}");
+    assert!(printed.contains("trace = true"), "{printed}");
+    let uart = &printed[printed.find("[peripherals.uart]").expect(&printed)..];
+    assert!(uart.contains("refs = true"), "{uart}");
+    assert!(
+        uart.contains("default_args = [\"Timeout=HAL_MAX_DELAY\"]"),
+        "{uart}"
+    );
+
+    let reprinted_config = dir.join("printed.toml");
+    std::fs::write(&reprinted_config, &printed).unwrap();
+    assert_eq!(print_config(&[&config_arg(&reprinted_config)]), printed);
+}
+
+#[test]
+fn command_line_overrides_config() {
+    let dir = common::outdir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    std::fs::write(&config, "strip_elaborated = false\n").unwrap();
+    let printed = print_config(&[&config_arg(&config), "--strip-elaborated=true"]);
+    assert!(printed.contains("strip_elaborated = true"), "{printed}");
+}
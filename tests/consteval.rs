@@ -0,0 +1,38 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn allow_listed_functions_are_consteval_copies() {
+    let options = Options {
+        cpp_standard: 20,
+        consteval: vec!["LL_CALC_Prescaler".to_owned()],
+        ..Options::default()
+    };
+    let code = common::convert("ll_calc", &options);
+    assert!(
+        common::method(&code, "prescaler").contains(
+            "consteval uint32_t prescaler(uint32_t Clock, uint32_t Freq) { return (Clock / Freq) - 1U; }"
+        ),
+        "{code}"
+    );
+    // The others still call the C function
+    assert!(
+        common::method(&code, "ratio").contains("{ return LL_CALC_Ratio(Num, Den); }"),
+        "{code}"
+    );
+    assert!(
+        !common::method(&code, "ratio").contains("consteval"),
+        "{code}"
+    );
+}
+
+#[test]
+fn consteval_requires_cpp20() {
+    let mut options = Options {
+        consteval: vec!["LL_CALC_Prescaler".to_owned()],
+        ..Options::default()
+    };
+    let err = options.resolve().unwrap_err();
+    assert!(err.to_string().contains("--cpp-standard 20"), "{err}");
+}
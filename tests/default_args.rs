@@ -0,0 +1,50 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::Options;
+
+#[test]
+fn trailing_parameters_get_defaults() {
+    let options = Options {
+        default_args: vec!["Timeout=HAL_MAX_DELAY".to_owned()],
+        ..Options::default()
+    };
+    let code = common::convert("hal_uart", &options);
+    let transmit = method(&code, "transmit");
+    assert!(
+        transmit.contains(
+            "transmit(const uint8_t *pData, uint16_t Size, uint32_t Timeout = HAL_MAX_DELAY)"
+        ),
+        "{transmit}"
+    );
+    assert!(transmit.contains("pData, Size, Timeout)"), "{transmit}");
+}
+
+#[test]
+fn defaults_must_be_trailing() {
+    let options = Options {
+        default_args: vec!["Size=1".to_owned()],
+        ..Options::default()
+    };
+    let err = common::convert_to("hal_uart", &common::outdir(), &options).unwrap_err();
+    assert!(err.to_string().contains("followed by Timeout"), "{err}");
+}
+
+#[test]
+fn the_output_parameter_needs_no_default() {
+    let options = Options {
+        out_params: true,
+        default_args: vec!["Channel=0".to_owned()],
+        ..Options::default()
+    };
+    let code = common::convert("ll_refs", &options);
+    let read = method(&code, "read");
+    assert!(
+        read.contains("REFS_Config read(uint32_t Channel = 0)"),
+        "{read}"
+    );
+    assert!(
+        read.contains("LL_REFS_Read(this->refs, Channel, &Config)"),
+        "{read}"
+    );
+}
@@ -0,0 +1,10 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn regenerating_gives_identical_output() {
+    let first = common::convert("hal_uart", &Options::default());
+    let second = common::convert("hal_uart", &Options::default());
+    assert_eq!(first, second);
+}
@@ -0,0 +1,13 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn one_class_per_handle_struct() {
+    let code = common::convert("hal_twin", &Options::default());
+    assert_eq!(code.matches("class ").count(), 1, "{code}");
+    // Whichever name they take it by
+    assert!(code.contains("HAL_TWIN_Start(this->twin)"), "{code}");
+    assert!(code.contains("HAL_TWIN_GetState(this->twin)"), "{code}");
+    assert!(code.contains("HAL_TWINEx_Stop(this->twin)"), "{code}");
+}
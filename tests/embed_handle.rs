@@ -0,0 +1,67 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::method;
+use sthal_to_cpp::{EmbedHandle, Options};
+
+/// The header generated from the fixture of `module` with `embed_handle`, and its path.
+fn convert(module: &str, embed_handle: EmbedHandle) -> (String, PathBuf) {
+    let options = Options {
+        embed_handle,
+        ..Options::default()
+    };
+    let outdir = common::outdir();
+    common::convert_to(module, &outdir, &options).unwrap();
+    let header = outdir.join(format!("{module}.hpp"));
+    (std::fs::read_to_string(&header).unwrap(), header)
+}
+
+#[test]
+fn pointer_stored_handles_are_forwarded() {
+    let (code, header) = convert("hal_uart", EmbedHandle::Pointer);
+    assert!(
+        code.contains("\t\t__UART_HandleTypeDef * uart;\n"),
+        "{code}"
+    );
+    let transmit = method(&code, "transmit");
+    assert!(
+        transmit.contains("HAL_UART_Transmit(this->uart, pData, Size, Timeout)"),
+        "{transmit}"
+    );
+    common::assert_compiles(&header, "hal_uart");
+}
+
+#[test]
+fn value_stored_handles_are_passed_by_address() {
+    let (code, header) = convert("hal_uart", EmbedHandle::Value);
+    assert!(code.contains("\t\t__UART_HandleTypeDef uart;\n"), "{code}");
+    let transmit = method(&code, "transmit");
+    assert!(
+        transmit.contains("HAL_UART_Transmit(&this->uart, pData, Size, Timeout)"),
+        "{transmit}"
+    );
+    common::assert_compiles(&header, "hal_uart");
+}
+
+#[test]
+fn inherited_handles_are_the_object() {
+    let (code, header) = convert("hal_uart", EmbedHandle::Inherit);
+    assert!(
+        code.contains("class Uart : public __UART_HandleTypeDef {"),
+        "{code}"
+    );
+    let transmit = method(&code, "transmit");
+    assert!(
+        transmit.contains("HAL_UART_Transmit(this, pData, Size, Timeout)"),
+        "{transmit}"
+    );
+    common::assert_compiles(&header, "hal_uart");
+}
+
+#[test]
+fn ll_classes_always_store_a_pointer() {
+    let (code, _) = convert("ll_tim", EmbedHandle::Value);
+    assert!(code.contains("\t\tTIM_TypeDef * tim;\n"), "{code}");
+    assert!(code.contains("LL_TIM_EnableCounter(this->tim)"), "{code}");
+}
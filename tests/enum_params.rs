@@ -0,0 +1,40 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::Options;
+
+#[test]
+fn enum_parameters_take_the_enum_class() {
+    let options = Options {
+        enums: true,
+        ..Options::default()
+    };
+    let outdir = common::outdir();
+    common::convert_to("ll_mode", &outdir, &options).unwrap();
+    let header = outdir.join("ll_mode.hpp");
+    let code = std::fs::read_to_string(&header).unwrap();
+    assert!(
+        code.contains("enum class ModeSpeed : std::underlying_type_t<MODE_SpeedTypeDef> {"),
+        "{code}"
+    );
+    let set_speed = method(&code, "setSpeed");
+    assert!(
+        set_speed.contains("setSpeed(ModeSpeed Speed)"),
+        "{set_speed}"
+    );
+    assert!(
+        set_speed.contains("LL_MODE_SetSpeed(this->mode, static_cast<MODE_SpeedTypeDef>(Speed))"),
+        "{set_speed}"
+    );
+    common::assert_compiles(&header, "ll_mode");
+}
+
+#[test]
+fn enum_parameters_stay_c_enums_without_enums() {
+    let code = common::convert("ll_mode", &Options::default());
+    let set_speed = method(&code, "setSpeed");
+    assert!(
+        set_speed.contains("setSpeed(MODE_SpeedTypeDef Speed)"),
+        "{set_speed}"
+    );
+}
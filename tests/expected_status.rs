@@ -0,0 +1,64 @@
+mod common;
+
+use std::process::Command;
+
+use common::method;
+use sthal_to_cpp::{Options, StatusMode};
+
+#[test]
+fn statuses_are_returned_as_expected() {
+    let options = Options {
+        status_mode: StatusMode::Expected,
+        cpp_standard: 23,
+        ..Options::default()
+    };
+    let code = common::convert("hal_uart", &options);
+    assert!(code.contains("#include <expected>"), "{code}");
+    let transmit = method(&code, "transmit");
+    assert!(
+        transmit.contains("std::expected<void, HAL_StatusTypeDef> transmit("),
+        "{transmit}"
+    );
+    assert!(
+        transmit.contains("status != HAL_OK) { return std::unexpected(status); } return {};"),
+        "{transmit}"
+    );
+    // Other results are returned as they are
+    let get_error = method(&code, "getError");
+    assert!(get_error.contains("uint32_t getError()"), "{get_error}");
+}
+
+#[test]
+fn expected_is_a_shorthand_requiring_cpp23() {
+    let mut options = Options {
+        expected: true,
+        ..Options::default()
+    };
+    let err = options.resolve().unwrap_err();
+    assert!(err.to_string().contains("--cpp-standard 23"), "{err}");
+
+    let mut options = Options {
+        expected: true,
+        cpp_standard: 23,
+        ..Options::default()
+    };
+    options.resolve().unwrap();
+    assert_eq!(options.status_mode, StatusMode::Expected);
+}
+
+#[test]
+fn expected_conflicts_with_other_status_modes() {
+    let output = Command::new(env!("CARGO_BIN_EXE_sthal_to_cpp"))
+        .args([
+            "in",
+            "out",
+            "--print-config",
+            "--expected",
+            "--status-mode=bool",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with"), "{stderr}");
+}
@@ -0,0 +1,25 @@
+#ifndef STM32F4xx_HAL_ALIAS_H
+#define STM32F4xx_HAL_ALIAS_H
+
+#include <stdint.h>
+
+typedef struct __ALIAS_HandleTypeDef {
+  uint32_t State;
+} ALIAS_HandleTypeDef;
+
+/* The same handle under another name */
+typedef ALIAS_HandleTypeDef ALIAS_Handle;
+
+/* Not the handle, its name only starts like it */
+typedef struct {
+  ALIAS_HandleTypeDef *Parent;
+} ALIAS_HandleTypeDef_Extra;
+
+void HAL_ALIAS_Start(ALIAS_HandleTypeDef *halias);
+void HAL_ALIAS_Stop(ALIAS_Handle *halias);
+uint32_t HAL_ALIAS_GetState(const ALIAS_HandleTypeDef *halias);
+void HAL_ALIAS_Attach(ALIAS_HandleTypeDef_Extra *extra);
+/* Spelled with the struct's tag */
+struct __ALIAS_HandleTypeDef *HAL_ALIAS_Swap(ALIAS_HandleTypeDef *halias, struct __ALIAS_HandleTypeDef *other);
+
+#endif
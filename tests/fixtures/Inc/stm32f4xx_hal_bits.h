@@ -0,0 +1,14 @@
+#ifndef STM32F4xx_HAL_BITS_H
+#define STM32F4xx_HAL_BITS_H
+
+#include <stdint.h>
+
+typedef struct {
+  uint32_t Mode : 3;
+  uint32_t Enable : 1;
+  uint32_t Prescaler;
+} BITS_InitTypeDef;
+
+void HAL_BITS_Reset(uint32_t Mask);
+
+#endif
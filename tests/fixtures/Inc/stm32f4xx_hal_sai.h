@@ -0,0 +1,24 @@
+#ifndef STM32F4xx_HAL_SAI_H
+#define STM32F4xx_HAL_SAI_H
+
+#include <stdint.h>
+
+typedef enum {
+  HAL_OK = 0x00U,
+  HAL_ERROR = 0x01U
+} HAL_StatusTypeDef;
+
+typedef struct {
+  void *Instance;
+} SAI_HandleTypeDef;
+
+typedef struct {
+  void *Instance;
+  uint32_t Block;
+} SAI_Block_HandleTypeDef;
+
+HAL_StatusTypeDef HAL_SAI_Init(SAI_HandleTypeDef *hsai);
+HAL_StatusTypeDef HAL_SAI_BlockStart(SAI_Block_HandleTypeDef *hblock);
+void HAL_Delay(uint32_t Delay);
+
+#endif
@@ -0,0 +1,21 @@
+#ifndef STM32F4xx_HAL_TWIN_H
+#define STM32F4xx_HAL_TWIN_H
+
+#include <stdint.h>
+
+typedef enum {
+  HAL_OK = 0x00U,
+  HAL_ERROR = 0x01U
+} HAL_StatusTypeDef;
+
+/* One handle struct under two names */
+typedef struct {
+  void *Instance;
+  uint32_t State;
+} TWIN_HandleTypeDef, TWINEx_HandleTypeDef;
+
+HAL_StatusTypeDef HAL_TWIN_Start(TWIN_HandleTypeDef *htwin);
+uint32_t HAL_TWIN_GetState(const TWIN_HandleTypeDef *htwin);
+HAL_StatusTypeDef HAL_TWINEx_Stop(TWINEx_HandleTypeDef *htwin);
+
+#endif
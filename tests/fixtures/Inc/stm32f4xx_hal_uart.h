@@ -0,0 +1,46 @@
+#ifndef STM32F4xx_HAL_UART_H
+#define STM32F4xx_HAL_UART_H
+
+#include <stdint.h>
+
+#define __STM32F4xx_HAL_VERSION_MAIN (0x01U)
+#define __STM32F4xx_HAL_VERSION_SUB1 (0x08U)
+#define __STM32F4xx_HAL_VERSION_SUB2 0x03U
+#define __STM32F4xx_HAL_VERSION_RC (0x00U)
+
+#define UART_WORDLENGTH_8B 0x00000000U
+#define UART_WORDLENGTH_9B ((uint32_t)0x00001000U)
+#define UART_FLAG_SET(__FLAG__) (__FLAG__)
+#define UART_DEFAULT_INSTANCE ((void *)0x40011000U)
+
+typedef enum {
+  HAL_OK = 0x00U,
+  HAL_ERROR = 0x01U,
+  HAL_BUSY = 0x02U,
+  HAL_TIMEOUT = 0x03U
+} HAL_StatusTypeDef;
+
+typedef struct {
+  uint32_t BaudRate;
+  uint32_t WordLength;
+} UART_InitTypeDef;
+
+typedef struct __UART_HandleTypeDef {
+  void *Instance;
+  UART_InitTypeDef Init;
+  uint32_t ErrorCode;
+} UART_HandleTypeDef;
+
+/** Initializes the UART */
+HAL_StatusTypeDef HAL_UART_Init(UART_HandleTypeDef *huart);
+HAL_StatusTypeDef HAL_UART_DeInit(UART_HandleTypeDef *huart);
+HAL_StatusTypeDef HAL_UART_Transmit(UART_HandleTypeDef *huart, const uint8_t *pData, uint16_t Size, uint32_t Timeout);
+HAL_StatusTypeDef HAL_UART_Receive(UART_HandleTypeDef *huart, uint8_t *pData, uint16_t Size, uint32_t Timeout);
+uint32_t HAL_UART_GetError(const UART_HandleTypeDef *huart);
+void HAL_UART_TxCpltCallback(UART_HandleTypeDef *huart);
+void HAL_UART_IRQHandler(UART_HandleTypeDef *huart);
+uint32_t HAL_UART_GetInstanceCount(void);
+uint32_t HAL_GetTick(void);
+uint32_t HAL_GetUid(void);
+
+#endif
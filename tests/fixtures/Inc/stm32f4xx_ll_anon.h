@@ -0,0 +1,14 @@
+#ifndef STM32F4xx_LL_ANON_H
+#define STM32F4xx_LL_ANON_H
+
+#include <stdint.h>
+
+typedef struct {
+  uint32_t CR;
+} ANON_TypeDef;
+
+void LL_ANON_Set(ANON_TypeDef *, uint32_t, uint32_t);
+uint32_t LL_ANON_Scale(uint32_t);
+void LL_ANON_Enable(ANON_TypeDef *ANONx);
+
+#endif
@@ -0,0 +1,11 @@
+#ifndef STM32F4xx_LL_ARR_H
+#define STM32F4xx_LL_ARR_H
+
+#include <stdint.h>
+
+void LL_ARR_Load(const uint8_t data[16]);
+void LL_ARR_Fill(uint8_t[4]);
+void LL_ARR_Matrix(uint32_t m[2][3]);
+void LL_ARR_Send(const uint8_t *pData, uint16_t Size);
+
+#endif
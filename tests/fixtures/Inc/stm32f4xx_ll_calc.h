@@ -0,0 +1,16 @@
+#ifndef STM32F4xx_LL_CALC_H
+#define STM32F4xx_LL_CALC_H
+
+#include <stdint.h>
+
+static inline uint32_t LL_CALC_Prescaler(uint32_t Clock, uint32_t Freq)
+{
+  return (Clock / Freq) - 1U;
+}
+
+static inline uint32_t LL_CALC_Ratio(uint32_t Num, uint32_t Den)
+{
+  return Num / Den;
+}
+
+#endif
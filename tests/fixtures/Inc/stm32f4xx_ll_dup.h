@@ -0,0 +1,11 @@
+#ifndef STM32F4xx_LL_DUP_H
+#define STM32F4xx_LL_DUP_H
+
+#include <stdint.h>
+
+void LL_DUP_Enable(uint32_t Channel);
+void LL_DUP_enable(unsigned int Mask);
+void LL_DUP_Start(void);
+void LL_DUPEx_Start(void);
+
+#endif
@@ -0,0 +1,8 @@
+#ifndef STM32F4xx_LL_FOO_H
+#define STM32F4xx_LL_FOO_H
+
+void LL_FOO_SetCallback(void (*cb)(void));
+void LL_FOO_SetHandler(void (*)(int));
+void (*LL_FOO_GetCallback(void))(void);
+
+#endif
@@ -0,0 +1,15 @@
+#ifndef STM32F4xx_LL_GPIO_H
+#define STM32F4xx_LL_GPIO_H
+
+#include <stdint.h>
+
+typedef struct {
+  volatile uint32_t MODER;
+  volatile uint32_t ODR;
+} GPIO_TypeDef;
+
+GPIO_TypeDef *LL_GPIO_GetPort(uint32_t Index);
+const uint32_t *LL_GPIO_GetModes(GPIO_TypeDef *GPIOx);
+void *LL_GPIO_GetContext(GPIO_TypeDef *GPIOx);
+
+#endif
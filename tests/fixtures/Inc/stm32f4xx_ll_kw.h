@@ -0,0 +1,12 @@
+#ifndef STM32F4xx_LL_KW_H
+#define STM32F4xx_LL_KW_H
+
+#include <stdint.h>
+
+/* Named after C++ keywords once the prefix is stripped */
+void LL_KW_Delete(void);
+void LL_KW_New(uint32_t Size);
+/* Nothing left once the prefix is stripped */
+void LL_KW_(void);
+
+#endif
@@ -0,0 +1,22 @@
+#ifndef STM32F4xx_LL_LPUART_H
+#define STM32F4xx_LL_LPUART_H
+
+#include <stdint.h>
+
+typedef struct {
+  volatile uint32_t CR1;
+  volatile uint32_t BRR;
+} USART_TypeDef;
+
+typedef struct {
+  uint32_t BaudRate;
+  uint32_t DataWidth;
+} LL_LPUART_InitTypeDef;
+
+void LL_LPUART_Enable(USART_TypeDef *LPUARTx);
+uint32_t LL_LPUART_IsEnabled(const USART_TypeDef *LPUARTx);
+void LL_LPUART_SetBaudRate(USART_TypeDef *LPUARTx, uint32_t PeriphClk, uint32_t BaudRate);
+uint32_t LL_LPUART_Init(USART_TypeDef *LPUARTx, const LL_LPUART_InitTypeDef *LPUART_InitStruct);
+void LL_LPUART_StructInit(LL_LPUART_InitTypeDef *LPUART_InitStruct);
+
+#endif
@@ -0,0 +1,17 @@
+#ifndef STM32F4xx_LL_MODE_H
+#define STM32F4xx_LL_MODE_H
+
+#include <stdint.h>
+
+typedef struct {
+  volatile uint32_t CR;
+} MODE_TypeDef;
+
+typedef enum {
+  MODE_SPEED_SLOW = 0,
+  MODE_SPEED_FAST = 1
+} MODE_SpeedTypeDef;
+
+void LL_MODE_SetSpeed(MODE_TypeDef *MODEx, MODE_SpeedTypeDef Speed);
+
+#endif
@@ -0,0 +1,22 @@
+#ifndef STM32F4xx_LL_REFS_H
+#define STM32F4xx_LL_REFS_H
+
+#include <stdint.h>
+
+typedef struct {
+  volatile uint32_t CR;
+} REFS_TypeDef;
+
+typedef struct {
+  uint32_t Mode;
+} REFS_Config;
+
+void LL_REFS_Configure(REFS_TypeDef *REFSx, REFS_Config *Config);
+void LL_REFS_Apply(REFS_TypeDef *REFSx, REFS_Config *);
+void LL_REFS_Check(REFS_TypeDef *REFSx, const REFS_Config *Config);
+void LL_REFS_Write(REFS_TypeDef *REFSx, uint8_t *pData, void *Context);
+void LL_REFS_Fill(REFS_TypeDef *REFSx, REFS_Config Configs[2]);
+void LL_REFS_Read(REFS_TypeDef *REFSx, uint32_t Channel, REFS_Config *Config);
+void LL_REFS_Setup(REFS_TypeDef *REFSx, REFS_Config *Config, uint32_t Count);
+
+#endif
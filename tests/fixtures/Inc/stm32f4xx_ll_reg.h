@@ -0,0 +1,17 @@
+#ifndef STM32F4xx_LL_REG_H
+#define STM32F4xx_LL_REG_H
+
+#include <stdint.h>
+
+#define __IO volatile
+
+typedef struct {
+  __IO uint32_t CR;
+  __IO uint32_t DR;
+} REG_TypeDef;
+
+void LL_REG_Read(REG_TypeDef *REGx, volatile uint32_t *pValue);
+void LL_REG_Write(REG_TypeDef *REGx, __IO uint32_t *);
+__IO uint32_t *LL_REG_Data(REG_TypeDef *REGx);
+
+#endif
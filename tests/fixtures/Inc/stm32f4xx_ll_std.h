@@ -0,0 +1,17 @@
+#ifndef STM32F4xx_LL_STD_H
+#define STM32F4xx_LL_STD_H
+
+#include <stdint.h>
+
+typedef struct {
+  volatile uint32_t CR;
+} STD_TypeDef;
+
+/* Declared depending on the C standard it's parsed with */
+#if __STDC_VERSION__ == 199901L
+void LL_STD_EnableC99(STD_TypeDef *STDx);
+#else
+void LL_STD_Enable(STD_TypeDef *STDx);
+#endif
+
+#endif
@@ -0,0 +1,20 @@
+#ifndef STM32F4xx_LL_TIM_H
+#define STM32F4xx_LL_TIM_H
+
+#include <stdint.h>
+
+typedef struct {
+  volatile uint32_t CR1;
+  volatile uint32_t CNT;
+  volatile uint32_t ARR;
+} TIM_TypeDef;
+
+/** Enables the counter */
+void LL_TIM_EnableCounter(TIM_TypeDef *TIMx);
+void LL_TIM_DisableCounter(TIM_TypeDef *TIMx);
+uint32_t LL_TIM_IsEnabledCounter(const TIM_TypeDef *TIMx);
+void LL_TIM_SetCounter(TIM_TypeDef *TIMx, uint32_t Counter);
+uint32_t LL_TIM_GetCounter(const TIM_TypeDef *TIMx);
+void LL_TIM_SetAutoReload(TIM_TypeDef *TIMx, uint32_t AutoReload);
+
+#endif
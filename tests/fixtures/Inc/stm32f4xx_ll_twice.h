@@ -0,0 +1,14 @@
+#ifndef STM32F4xx_LL_TWICE_H
+#define STM32F4xx_LL_TWICE_H
+
+#include <stdint.h>
+
+typedef struct {
+  volatile uint32_t CR;
+} TWICE_TypeDef;
+
+void LL_TWICE_Enable(TWICE_TypeDef *TWICEx);
+/* Declared again, like a prototype before its inline definition */
+void LL_TWICE_Enable(TWICE_TypeDef *TWICEx);
+
+#endif
@@ -0,0 +1,10 @@
+#ifndef STM32F4xx_LL_TWICE_EX_H
+#define STM32F4xx_LL_TWICE_EX_H
+
+#include "stm32f4xx_ll_twice.h"
+
+/* Declared a third time, in the extension module */
+void LL_TWICE_Enable(TWICE_TypeDef *TWICEx);
+void LL_TWICEEx_Start(TWICE_TypeDef *TWICEx);
+
+#endif
@@ -0,0 +1,30 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+fn options() -> Options {
+    Options {
+        friends: vec![
+            "struct Registry<{class}>".to_owned(),
+            "void dump()".to_owned(),
+        ],
+        ..Options::default()
+    }
+}
+
+#[test]
+fn handle_classes_declare_the_friends() {
+    let code = common::convert("hal_uart", &options());
+    assert!(
+        code.contains(
+            "\tclass Uart {\n\t\tfriend struct Registry<Uart>;\n\t\tfriend void dump();\n\tpublic:\n"
+        ),
+        "{code}"
+    );
+}
+
+#[test]
+fn namespaces_get_no_friends() {
+    let code = common::convert("ll_dup", &options());
+    assert!(!code.contains("friend"), "{code}");
+}
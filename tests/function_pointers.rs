@@ -0,0 +1,29 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn function_pointer_parameters_are_declared_and_forwarded() {
+    let code = common::convert("ll_foo", &Options::default());
+    assert!(code.contains("setCallback(void (*cb)(void))"), "{code}");
+    assert!(code.contains("LL_FOO_SetCallback(cb)"), "{code}");
+    assert!(code.contains("setHandler(void (*arg0)(int))"), "{code}");
+    assert!(code.contains("LL_FOO_SetHandler(arg0)"), "{code}");
+}
+
+#[test]
+fn function_pointer_results_get_an_alias() {
+    let outdir = common::outdir();
+    common::convert_to("ll_foo", &outdir, &Options::default()).unwrap();
+    let header = outdir.join("ll_foo.hpp");
+    let code = std::fs::read_to_string(&header).unwrap();
+    assert!(
+        code.contains("using GetCallbackResult = void (*)(void);\n"),
+        "{code}"
+    );
+    assert!(
+        code.contains("GetCallbackResult getCallback() { return LL_FOO_GetCallback(); }"),
+        "{code}"
+    );
+    common::assert_compiles(&header, "ll_foo");
+}
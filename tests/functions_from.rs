@@ -0,0 +1,46 @@
+mod common;
+
+use std::sync::Mutex;
+
+use fxhash::FxHashSet;
+use sthal_to_cpp::Options;
+
+#[test]
+fn only_listed_functions_are_wrapped() {
+    let outdir = common::outdir();
+    std::fs::create_dir_all(&outdir).unwrap();
+    let list = outdir.join("functions.txt");
+    std::fs::write(
+        &list,
+        "# The UART surface\nHAL_UART_Transmit\nHAL_UART_TxCpltCallback  # even callbacks\n\nHAL_UART_Missing\n",
+    )
+    .unwrap();
+    let mut options = Options {
+        functions_from: Some(list),
+        ..Options::default()
+    };
+    options.resolve().unwrap();
+    let listed_found = Mutex::default();
+    common::convert_among(
+        &common::fixture("hal_uart"),
+        &outdir,
+        &FxHashSet::default(),
+        &listed_found,
+        &options,
+    )
+    .unwrap();
+    let code = std::fs::read_to_string(outdir.join("hal_uart.hpp")).unwrap();
+    assert!(code.contains(" transmit("), "{code}");
+    assert!(code.contains(" txCpltCallback("), "{code}");
+    assert!(!code.contains(" receive("), "{code}");
+    assert!(!code.contains(" init("), "{code}");
+
+    let listed_found = listed_found.into_inner().unwrap();
+    let missing = options
+        .function_list
+        .unwrap()
+        .difference(&listed_found)
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(missing, ["HAL_UART_Missing"]);
+}
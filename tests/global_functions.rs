@@ -0,0 +1,30 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::Options;
+
+#[test]
+fn global_functions_go_in_core() {
+    let code = common::convert("hal_sai", &Options::default());
+    let core = code
+        .find("namespace hal {\n\tnamespace Core {\n")
+        .expect(&code);
+    let delay = method(&code, "delay");
+    assert_eq!(
+        delay,
+        "\t\tstatic inline void delay(uint32_t Delay) { return HAL_Delay(Delay); }"
+    );
+    assert!(code.find(delay).unwrap() > core, "{code}");
+    assert!(code.contains("#ifndef STHAL_HAL_CORE"), "{code}");
+}
+
+#[test]
+fn global_functions_are_configurable() {
+    let options = Options {
+        global_functions: vec!["HAL_GetTick".to_owned()],
+        ..Options::default()
+    };
+    let code = common::convert("hal_sai", &options);
+    assert!(!code.contains("HAL_Delay"), "{code}");
+    assert!(!code.contains("namespace Core"), "{code}");
+}
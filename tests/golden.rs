@@ -0,0 +1,39 @@
+//! Compares the headers generated from the fixtures with the `golden/*.expected.hpp` files.
+//! Run with `UPDATE_GOLDEN=1` to regenerate those after an intended change to the output, or
+//! to write the file of a new module, and review and commit them.
+
+mod common;
+
+use std::path::Path;
+
+use sthal_to_cpp::Options;
+
+const MODULES: [&str; 2] = ["hal_uart", "ll_tim"];
+
+#[test]
+fn output_matches_golden_files() {
+    let golden_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some_and(|value| value == "1");
+
+    let mut mismatches = vec![];
+    for module in MODULES {
+        let code = common::convert(module, &Options::default());
+        let golden = golden_dir.join(format!("{module}.expected.hpp"));
+        if update {
+            std::fs::create_dir_all(&golden_dir).unwrap();
+            std::fs::write(&golden, &code).unwrap();
+            eprintln!("Wrote {}", golden.display());
+            continue;
+        }
+        match std::fs::read_to_string(&golden) {
+            Ok(expected) if expected == code => {}
+            Ok(_) => mismatches.push(golden.display().to_string()),
+            Err(e) => mismatches.push(format!("{} ({e})", golden.display())),
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "The output doesn't match {}, rerun with UPDATE_GOLDEN=1 if that's intended",
+        mismatches.join(", ")
+    );
+}
@@ -0,0 +1,24 @@
+#pragma once
+#include "stm32f4xx_hal.h"
+namespace hal {
+	class Uart {
+	public:
+		__UART_HandleTypeDef * uart;
+		Uart(__UART_HandleTypeDef * uart) : uart(uart) {}
+		/** Initializes the UART */
+		inline HAL_StatusTypeDef init() { return HAL_UART_Init(this->uart); }
+		inline HAL_StatusTypeDef deInit() { return HAL_UART_DeInit(this->uart); }
+		inline HAL_StatusTypeDef transmit(const uint8_t *pData, uint16_t Size, uint32_t Timeout) { return HAL_UART_Transmit(this->uart, pData, Size, Timeout); }
+		inline HAL_StatusTypeDef receive(uint8_t *pData, uint16_t Size, uint32_t Timeout) { return HAL_UART_Receive(this->uart, pData, Size, Timeout); }
+		inline uint32_t getError() const { return HAL_UART_GetError(this->uart); }
+		static inline uint32_t getInstanceCount() { return HAL_UART_GetInstanceCount(); }
+	};
+}
+#ifndef STHAL_HAL_CORE
+#define STHAL_HAL_CORE
+namespace hal {
+	namespace Core {
+		static inline uint32_t getTick() { return HAL_GetTick(); }
+	}
+}
+#endif
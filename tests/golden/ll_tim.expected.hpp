@@ -0,0 +1,16 @@
+#pragma once
+#include "stm32f4xx_ll_tim.h"
+namespace ll {
+	class Tim {
+	public:
+		TIM_TypeDef * tim;
+		Tim(TIM_TypeDef * tim) : tim(tim) {}
+		/** Enables the counter */
+		inline void enableCounter() { return LL_TIM_EnableCounter(this->tim); }
+		inline void disableCounter() { return LL_TIM_DisableCounter(this->tim); }
+		inline uint32_t isEnabledCounter() const { return LL_TIM_IsEnabledCounter(this->tim); }
+		inline void setCounter(uint32_t Counter) { return LL_TIM_SetCounter(this->tim, Counter); }
+		inline uint32_t getCounter() const { return LL_TIM_GetCounter(this->tim); }
+		inline void setAutoReload(uint32_t AutoReload) { return LL_TIM_SetAutoReload(this->tim, AutoReload); }
+	};
+}
@@ -0,0 +1,12 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn several_handle_classes_share_a_namespace() {
+    let code = common::convert("hal_sai", &Options::default());
+    let periph = code.find("namespace hal {\n\tnamespace Sai {\n").unwrap();
+    let sai = code.find("\t\tclass Sai {").unwrap();
+    let block = code.find("\t\tclass SaiBlock {").unwrap();
+    assert!(periph < sai && periph < block, "{code}");
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use fxhash::FxHashSet;
+use sthal_to_cpp::{hal_ll_overlap, NamespaceLayout, Options};
+
+#[test]
+fn peripherals_with_hal_and_ll_modules_overlap() {
+    let files = [
+        "Src/stm32f4xx_hal_tim.c",
+        "Inc/stm32f4xx_ll_tim.h",
+        "Src/stm32f4xx_hal_uart.c",
+        "Inc/stm32f4xx_ll_gpio.h",
+    ]
+    .map(PathBuf::from);
+    let overlap = hal_ll_overlap(files.iter());
+    assert_eq!(overlap, FxHashSet::from_iter(["tim".to_owned()]));
+}
+
+#[test]
+fn merged_layout_keeps_hal_and_ll_classes_apart() {
+    let options = Options {
+        namespace_layout: NamespaceLayout::Merged,
+        ..Options::default()
+    };
+    let file = common::fixture("ll_tim");
+    let header = |overlap: &[&str]| {
+        let outdir = common::outdir();
+        let overlap = overlap.iter().map(|&periph| periph.to_owned()).collect();
+        common::convert_among(&file, &outdir, &overlap, &Mutex::default(), &options).unwrap();
+        std::fs::read_to_string(outdir.join("ll_tim.hpp")).unwrap()
+    };
+
+    let code = header(&["tim"]);
+    assert!(
+        code.contains(
+            "namespace stm32 {\n\tnamespace Tim {\n\t\tnamespace ll {\n\t\t\tclass Tim {"
+        ),
+        "{code}"
+    );
+    let code = header(&[]);
+    assert!(
+        code.contains("namespace stm32 {\n\tnamespace Tim {\n\t\tclass Tim {"),
+        "{code}"
+    );
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use clap::Parser;
+use sthal_to_cpp::Options;
+
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    options: Options,
+}
+
+#[test]
+fn handle_name_must_be_an_identifier() {
+    let parse = |name: &str| Cli::try_parse_from(["sthal_to_cpp", "--handle-name", name]);
+    assert!(parse("m_handle").is_ok());
+    assert!(parse("m handle").is_err());
+    assert!(parse("2handle").is_err());
+    assert!(parse("this").is_err());
+}
+
+#[test]
+fn handle_field_is_renamed() {
+    let options = Options {
+        handle_name: Some("m_handle".to_owned()),
+        ..Options::default()
+    };
+    let code = common::convert("hal_uart", &options);
+    assert!(code.contains(" m_handle;"), "{code}");
+    assert!(
+        code.contains("HAL_UART_Transmit(this->m_handle, "),
+        "{code}"
+    );
+    assert!(!code.contains("this->uart"), "{code}");
+}
@@ -0,0 +1,14 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn receiver_is_matched_by_type() {
+    let code = common::convert("hal_alias", &Options::default());
+    assert!(code.contains("HAL_ALIAS_Start(this->alias)"), "{code}");
+    // Through a typedef of the handle
+    assert!(code.contains("HAL_ALIAS_Stop(this->alias)"), "{code}");
+    assert!(code.contains("getState() const"), "{code}");
+    // A different struct whose name starts with the handle's
+    assert!(!code.contains("HAL_ALIAS_Attach"), "{code}");
+}
@@ -0,0 +1,25 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::Options;
+
+#[test]
+fn irq_handlers_are_skipped_by_default() {
+    let code = common::convert("hal_uart", &Options::default());
+    assert!(!code.contains("IRQHandler"), "{code}");
+}
+
+#[test]
+fn irq_handlers_are_wrapped_on_request() {
+    let options = Options {
+        irq_handlers: true,
+        ..Options::default()
+    };
+    let code = common::convert("hal_uart", &options);
+    let handler = method(&code, "handleIrq");
+    assert!(handler.contains("void handleIrq() {"), "{handler}");
+    assert!(
+        handler.contains("HAL_UART_IRQHandler(this->uart)"),
+        "{handler}"
+    );
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use clang::sonar::find_functions;
+use sthal_to_cpp::{class_name, find_handle_types, parse_header, Options};
+
+#[test]
+fn steps_of_the_conversion_are_public() {
+    let options = Options::default();
+    common::with_clang(|index, db| {
+        let unit = parse_header(index, Some(db), &common::fixture("hal_uart"), &options).unwrap();
+        let functions = find_functions(unit.get_entity().get_children()).collect::<Vec<_>>();
+        assert!(functions
+            .iter()
+            .any(|decl| decl.name == "HAL_UART_Transmit"));
+        let handle_types = find_handle_types("hal", &unit, "uart", &functions);
+        let classes = handle_types
+            .iter()
+            .filter_map(|(name, _)| class_name(name))
+            .collect::<Vec<_>>();
+        assert_eq!(classes, ["Uart"]);
+    });
+}
+
+#[test]
+fn convert_header_writes_the_wrapper() {
+    let outdir = common::outdir();
+    let message = common::convert_to("ll_tim", &outdir, &Options::default()).unwrap();
+    let header = outdir.join("ll_tim.hpp");
+    assert!(message.contains(&header.display().to_string()), "{message}");
+    let code = std::fs::read_to_string(&header).unwrap();
+    assert!(code.contains("class Tim {"), "{code}");
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn instance_pointers_become_classes() {
+    let code = common::convert("ll_tim", &Options::default());
+    assert!(code.contains("class Tim"), "{code}");
+    assert!(
+        code.contains("Tim(TIM_TypeDef * tim) : tim(tim) {}"),
+        "{code}"
+    );
+    assert!(code.contains("LL_TIM_EnableCounter(this->tim)"), "{code}");
+    assert!(
+        code.contains("LL_TIM_SetCounter(this->tim, Counter)"),
+        "{code}"
+    );
+    assert!(code.contains("getCounter() const"), "{code}");
+}
+
+#[test]
+fn instance_parameters_are_matched_by_name() {
+    // The register block is shared with another peripheral, but the parameter is named after
+    // this one
+    let code = common::convert("ll_lpuart", &Options::default());
+    assert!(code.contains("class Usart"), "{code}");
+    assert!(code.contains("LL_LPUART_Enable(this->lpuart)"), "{code}");
+    assert!(code.contains("isEnabled() const"), "{code}");
+    assert!(
+        code.contains("LL_LPUART_SetBaudRate(this->lpuart, PeriphClk, BaudRate)"),
+        "{code}"
+    );
+    assert!(
+        code.contains("LL_LPUART_Init(this->lpuart, LPUART_InitStruct)"),
+        "{code}"
+    );
+    assert!(!code.contains("LL_LPUART_StructInit(this"), "{code}");
+}
@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use itertools::Itertools;
+use sthal_to_cpp::{render_headers, Header, Options};
+
+fn header(includes: &[&str]) -> Header {
+    Header {
+        includes: includes.iter().map(|&include| include.to_owned()).collect(),
+        ..Header::default()
+    }
+}
+
+#[test]
+fn merged_includes_are_sorted_and_unique() {
+    let headers = [
+        header(&["\"stm32f4xx_hal_uart.h\"", "<type_traits>", "<expected>"]),
+        header(&["\"stm32f4xx_hal_gpio.h\"", "<expected>", "<cstdint>"]),
+        header(&["\"stm32f4xx_hal_uart.h\""]),
+    ];
+    let code = render_headers(&headers, Path::new("stm32.hpp"), &Options::default()).unwrap();
+    let includes = code
+        .lines()
+        .filter(|line| line.starts_with("#include"))
+        .collect_vec();
+    assert_eq!(
+        includes,
+        [
+            "#include \"stm32f4xx_hal_gpio.h\"",
+            "#include \"stm32f4xx_hal_uart.h\"",
+            "#include <cstdint>",
+            "#include <expected>",
+            "#include <type_traits>",
+        ]
+    );
+    // After the guard
+    assert!(code.starts_with("#pragma once\n#include"), "{code}");
+}
@@ -0,0 +1,15 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn colliding_methods_are_renamed_or_dropped() {
+    let code = common::convert("ll_dup", &Options::default());
+    // `uint32_t` and `unsigned int` are the same type, the second can't overload the first
+    assert_eq!(code.matches(" enable(").count(), 1, "{code}");
+    assert!(code.contains("LL_DUP_Enable(Channel)"), "{code}");
+    assert!(!code.contains("LL_DUP_enable"), "{code}");
+    assert!(code.contains(" start()"), "{code}");
+    assert!(code.contains(" startEx()"), "{code}");
+    assert!(code.contains("LL_DUPEx_Start()"), "{code}");
+}
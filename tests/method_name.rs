@@ -0,0 +1,44 @@
+use sthal_to_cpp::method_name;
+
+#[test]
+fn strips_module_prefix() {
+    assert_eq!(method_name("HAL_UART_Transmit", "uart"), "transmit");
+    assert_eq!(method_name("LL_TIM_SetCounter", "tim"), "setCounter");
+}
+
+#[test]
+fn strips_extension_prefix() {
+    assert_eq!(
+        method_name("HAL_UARTEx_WakeupCallback", "uart"),
+        "wakeupCallback"
+    );
+    assert_eq!(
+        method_name("HAL_TIMEx_HallSensor_Init", "tim"),
+        "hallSensorInit"
+    );
+}
+
+#[test]
+fn keeps_other_peripherals() {
+    assert_ne!(method_name("HAL_I2C4_Init", "i2c"), "init");
+    assert_eq!(
+        method_name("HAL_MultiProcessor_Init", "uart"),
+        "multiProcessorInit"
+    );
+}
+
+#[test]
+fn strips_only_once() {
+    assert_eq!(method_name("HAL_DMA_DMA_Foo", "dma"), "dmaFoo");
+}
+
+#[test]
+fn without_prefix() {
+    assert_eq!(method_name("Init", "uart"), "");
+}
+
+#[test]
+fn irq_handlers() {
+    assert_eq!(method_name("HAL_UART_IRQHandler", "uart"), "handleIrq");
+    assert_eq!(method_name("HAL_RCC_NMI_IRQHandler", "rcc"), "handleNmiIrq");
+}
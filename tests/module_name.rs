@@ -0,0 +1,54 @@
+mod common;
+
+use std::path::Path;
+
+use sthal_to_cpp::{module_name, Options};
+
+#[test]
+fn hal_module() {
+    let name = module_name(Path::new("Src/stm32f4xx_hal_tim.c")).unwrap();
+    assert_eq!(name.stver, "stm32f4xx");
+    assert_eq!(name.hal_type, "hal");
+    assert_eq!(name.periph_type, "tim");
+}
+
+#[test]
+fn extension_module_is_skipped() {
+    assert!(module_name(Path::new("Src/stm32f4xx_hal_tim_ex.c")).is_err());
+}
+
+#[test]
+fn ll_module() {
+    let name = module_name(Path::new("Inc/stm32h7xx_ll_dmamux.h")).unwrap();
+    assert_eq!(name.stver, "stm32h7xx");
+    assert_eq!(name.hal_type, "ll");
+    assert_eq!(name.periph_type, "dmamux");
+}
+
+#[test]
+fn further_dots() {
+    let name = module_name(Path::new("Inc/stm32f4xx_ll_tim.g.h")).unwrap();
+    assert_eq!(name.periph_type, "tim");
+    assert_eq!(name.fname, "ll_tim");
+    // The LL wrappers include the file itself
+    assert_eq!(name.stem, "stm32f4xx_ll_tim.g");
+}
+
+#[test]
+fn compound_extension_is_converted() {
+    let dir = common::outdir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("stm32f4xx_ll_tim.g.h");
+    std::fs::copy(common::fixture("ll_tim"), &file).unwrap();
+    let outdir = dir.join("out");
+    common::convert_file(&file, &outdir, &Options::default()).unwrap();
+    let code = std::fs::read_to_string(outdir.join("ll_tim.hpp")).unwrap();
+    assert!(code.contains("#include \"stm32f4xx_ll_tim.g.h\""), "{code}");
+    assert!(code.contains("class Tim {"), "{code}");
+}
+
+#[test]
+fn missing_peripheral() {
+    assert!(module_name(Path::new("Inc/stm32f4xx_hal.h")).is_err());
+    assert!(module_name(Path::new("Inc/stm32f4xx_hal_.h")).is_err());
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn directories_become_namespaces() {
+    let root = common::outdir();
+    let dir = root.join("Src").join("my-periphs");
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("stm32f4xx_ll_tim.h");
+    std::fs::copy(common::fixture("ll_tim"), &file).unwrap();
+    let options = Options {
+        namespace_from_path: true,
+        path_roots: vec![root.clone()],
+        ..Options::default()
+    };
+    let outdir = root.join("out");
+    common::convert_file(&file, &outdir, &options).unwrap();
+    let code = std::fs::read_to_string(outdir.join("ll_tim.hpp")).unwrap();
+    // Made valid identifiers
+    assert!(
+        code.contains(
+            "namespace Src {\n\tnamespace my_periphs {\n\t\tnamespace ll {\n\t\t\tclass Tim {"
+        ),
+        "{code}"
+    );
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use sthal_to_cpp::{NamespaceLayout, Options};
+
+#[test]
+fn nested_layout_has_a_namespace_per_level() {
+    let options = Options {
+        namespace_layout: NamespaceLayout::Nested,
+        ..Options::default()
+    };
+    let code = common::convert("hal_uart", &options);
+    assert!(
+        code.contains(
+            "namespace stm32 {\n\tnamespace hal {\n\t\tnamespace Uart {\n\t\t\tclass Uart {"
+        ),
+        "{code}"
+    );
+    assert!(
+        code.contains("namespace stm32 {\n\tnamespace hal {\n\t\tnamespace Core {"),
+        "{code}"
+    );
+
+    let code = common::convert("ll_tim", &options);
+    assert!(
+        code.contains(
+            "namespace stm32 {\n\tnamespace ll {\n\t\tnamespace Tim {\n\t\t\tclass Tim {"
+        ),
+        "{code}"
+    );
+}
+
+#[test]
+fn merged_layout_drops_the_hal_level() {
+    let options = Options {
+        namespace_layout: NamespaceLayout::Merged,
+        root_namespace: "chip".to_owned(),
+        ..Options::default()
+    };
+    let code = common::convert("ll_tim", &options);
+    assert!(
+        code.contains("namespace chip {\n\tnamespace Tim {\n\t\tclass Tim {"),
+        "{code}"
+    );
+    assert!(!code.contains("namespace ll"), "{code}");
+}
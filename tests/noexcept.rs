@@ -0,0 +1,25 @@
+mod common;
+
+use sthal_to_cpp::{Options, StatusMode};
+
+#[test]
+fn noexcept_wrappers() {
+    let options = Options {
+        noexcept: true,
+        ..Options::default()
+    };
+    let code = common::convert("hal_uart", &options);
+    assert!(code.contains("getError() const noexcept {"), "{code}");
+    assert!(code.contains("getInstanceCount() noexcept {"), "{code}");
+}
+
+#[test]
+fn throwing_wrappers_are_not_noexcept() {
+    let options = Options {
+        noexcept: true,
+        status_mode: StatusMode::Throw,
+        ..Options::default()
+    };
+    let code = common::convert("hal_uart", &options);
+    assert!(!code.contains("noexcept"), "{code}");
+}
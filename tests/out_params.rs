@@ -0,0 +1,44 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::Options;
+
+#[test]
+fn trailing_struct_pointer_is_returned() {
+    let options = Options {
+        out_params: true,
+        ..Options::default()
+    };
+    let outdir = common::outdir();
+    common::convert_to("ll_refs", &outdir, &options).unwrap();
+    let header = outdir.join("ll_refs.hpp");
+    let code = std::fs::read_to_string(&header).unwrap();
+    let read = method(&code, "read");
+    assert!(
+        read.contains("REFS_Config read(uint32_t Channel)"),
+        "{read}"
+    );
+    assert!(
+        read.contains("LL_REFS_Read(this->refs, Channel, &Config)"),
+        "{read}"
+    );
+    common::assert_compiles(&header, "ll_refs");
+}
+
+#[test]
+fn struct_pointer_followed_by_parameters_is_kept() {
+    let options = Options {
+        out_params: true,
+        ..Options::default()
+    };
+    let code = common::convert("ll_refs", &options);
+    let setup = method(&code, "setup");
+    assert!(
+        setup.contains("void setup(REFS_Config *Config, uint32_t Count)"),
+        "{setup}"
+    );
+    assert!(
+        setup.contains("LL_REFS_Setup(this->refs, Config, Count)"),
+        "{setup}"
+    );
+}
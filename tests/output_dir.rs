@@ -0,0 +1,38 @@
+mod common;
+
+use sthal_to_cpp::{write_output, Options, OutputLayout};
+
+#[test]
+fn missing_output_directories_are_created() {
+    let outdir = common::outdir().join("a/b");
+    let options = Options {
+        layout: OutputLayout::ByVersion,
+        ..Options::default()
+    };
+    common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(outdir.join("stm32f4xx/hal_uart.hpp").is_file());
+}
+
+#[test]
+fn write_output_creates_the_parent_directories() {
+    let path = common::outdir().join("nested/dir/ll_tim.hpp");
+    write_output(&path, "#pragma once\n", &Options::default()).unwrap();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "#pragma once\n");
+}
+
+#[test]
+fn output_directory_that_cannot_be_created_is_reported() {
+    let outdir = common::outdir();
+    std::fs::create_dir_all(&outdir).unwrap();
+    // A file where the directory should be
+    let blocker = outdir.join("stm32f4xx");
+    std::fs::write(&blocker, "").unwrap();
+    let err = write_output(&blocker.join("hal_uart.hpp"), "", &Options::default()).unwrap_err();
+    assert!(
+        err.to_string().contains(&format!(
+            "Could not create the output directory {}",
+            blocker.display()
+        )),
+        "{err}"
+    );
+}
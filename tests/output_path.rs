@@ -0,0 +1,75 @@
+use std::path::Path;
+use std::process::Command;
+
+use sthal_to_cpp::{module_name, output_path, Options, OutputLayout};
+
+#[test]
+fn default_name() {
+    let name = module_name(Path::new("Src/stm32f4xx_hal_uart.c")).unwrap();
+    let path = output_path(&name, Path::new("out"), &Options::default());
+    assert_eq!(path, Path::new("out/hal_uart.hpp"));
+}
+
+#[test]
+fn prefix_and_extension() {
+    let name = module_name(Path::new("Inc/stm32f4xx_ll_tim.h")).unwrap();
+    let options = Options {
+        out_ext: "hxx".to_owned(),
+        out_prefix: "sthal_".to_owned(),
+        ..Options::default()
+    };
+    let path = output_path(&name, Path::new("out"), &options);
+    assert_eq!(path, Path::new("out/sthal_ll_tim.hxx"));
+}
+
+#[test]
+fn hal_and_ll_modules_of_a_peripheral_do_not_collide() {
+    let hal = module_name(Path::new("Src/stm32f4xx_hal_uart.c")).unwrap();
+    let ll = module_name(Path::new("Inc/stm32f4xx_ll_uart.h")).unwrap();
+    let options = Options::default();
+    assert_ne!(
+        output_path(&hal, Path::new("out"), &options),
+        output_path(&ll, Path::new("out"), &options)
+    );
+}
+
+#[test]
+fn families_collide_in_the_flat_layout() {
+    let f4 = module_name(Path::new("Src/stm32f4xx_hal_uart.c")).unwrap();
+    let f7 = module_name(Path::new("Src/stm32f7xx_hal_uart.c")).unwrap();
+    let options = Options::default();
+    assert_eq!(
+        output_path(&f4, Path::new("out"), &options),
+        output_path(&f7, Path::new("out"), &options)
+    );
+    let options = Options {
+        layout: OutputLayout::ByVersion,
+        ..Options::default()
+    };
+    assert_ne!(
+        output_path(&f4, Path::new("out"), &options),
+        output_path(&f7, Path::new("out"), &options)
+    );
+}
+
+#[test]
+fn colliding_outputs_are_reported() {
+    let dir = std::env::temp_dir().join(format!("sthal_to_cpp_collide_{}", std::process::id()));
+    let src = dir.join("in/Src");
+    std::fs::create_dir_all(&src).unwrap();
+    for family in ["stm32f4xx", "stm32f7xx"] {
+        std::fs::write(src.join(format!("{family}_hal_uart.c")), "").unwrap();
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_sthal_to_cpp"))
+        .arg(dir.join("in"))
+        .arg(dir.join("out"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(
+        stderr.contains("are all converted to") && stderr.contains("hal_uart.hpp"),
+        "{stderr}"
+    );
+}
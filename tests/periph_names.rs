@@ -0,0 +1,23 @@
+use sthal_to_cpp::{mentions_periph, method_name};
+
+#[test]
+fn module_names_match_any_case() {
+    assert!(mentions_periph("HAL_I2S_Init", "i2s"));
+    assert!(mentions_periph("HAL_I2SEx_TransmitReceive", "i2s"));
+    assert!(mentions_periph("__SAI_HandleTypeDef", "sai"));
+    assert!(mentions_periph("SPDIFRX_HandleTypeDef", "SPDIFRX"));
+    assert!(mentions_periph("HAL_SPDIFRX_ReceiveDataFlow", "SpdifRx"));
+    assert!(!mentions_periph("HAL_SPI_Init", "i2s"));
+}
+
+#[test]
+fn mixed_case_periph_prefix() {
+    assert_eq!(
+        method_name("HAL_I2SEx_TransmitReceive", "I2s"),
+        "transmitReceive"
+    );
+    assert_eq!(
+        method_name("HAL_SPDIFRX_ReceiveDataFlow", "spdifrx"),
+        "receiveDataFlow"
+    );
+}
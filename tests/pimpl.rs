@@ -0,0 +1,43 @@
+mod common;
+
+use sthal_to_cpp::{EmbedHandle, Options};
+
+#[test]
+fn handle_is_hidden_behind_the_impl() {
+    let outdir = common::outdir();
+    let options = Options {
+        pimpl: true,
+        ..Options::default()
+    };
+    common::convert_to("hal_uart", &outdir, &options).unwrap();
+    let (header, source) = (outdir.join("hal_uart.hpp"), outdir.join("hal_uart.cpp"));
+    let code = std::fs::read_to_string(&header).unwrap();
+    assert!(code.contains("#include <memory>"), "{code}");
+    assert!(code.contains("struct Impl;"), "{code}");
+    assert!(code.contains("std::unique_ptr<Impl> impl;"), "{code}");
+    assert!(code.contains("~Uart();"), "{code}");
+    assert!(code.contains("HAL_StatusTypeDef init();"), "{code}");
+    assert!(!code.contains("HAL_UART_Init("), "{code}");
+
+    let code = std::fs::read_to_string(&source).unwrap();
+    assert!(code.contains("#include \"hal_uart.hpp\""), "{code}");
+    assert!(code.contains("struct Uart::Impl {"), "{code}");
+    assert!(code.contains("Uart::~Uart() = default;"), "{code}");
+    assert!(
+        code.contains("HAL_StatusTypeDef Uart::init() { return HAL_UART_Init(impl->uart); }"),
+        "{code}"
+    );
+    common::assert_compiles(&header, "hal_uart");
+    common::assert_compiles(&source, "hal_uart");
+}
+
+#[test]
+fn pimpl_requires_a_handle_pointer() {
+    let mut options = Options {
+        pimpl: true,
+        embed_handle: EmbedHandle::Value,
+        ..Options::default()
+    };
+    let err = options.resolve().unwrap_err();
+    assert!(err.to_string().contains("--embed-handle pointer"), "{err}");
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::Options;
+
+#[test]
+fn pointer_returns_are_forwarded() {
+    let code = common::convert("ll_gpio", &Options::default());
+    let get_port = method(&code, "getPort");
+    assert!(get_port.contains("GPIO_TypeDef *"), "{get_port}");
+    assert!(
+        get_port.contains("return LL_GPIO_GetPort(Index);"),
+        "{get_port}"
+    );
+    let get_modes = method(&code, "getModes");
+    assert!(get_modes.contains("const uint32_t *"), "{get_modes}");
+}
+
+#[test]
+fn ref_returns_are_dereferenced() {
+    let options = Options {
+        ref_returns: ["LL_GPIO_GetPort", "LL_GPIO_GetModes", "LL_GPIO_GetContext"]
+            .map(String::from)
+            .to_vec(),
+        ..Options::default()
+    };
+    let code = common::convert("ll_gpio", &options);
+    let get_port = method(&code, "getPort");
+    assert!(get_port.contains("GPIO_TypeDef &"), "{get_port}");
+    assert!(
+        get_port.contains("return *LL_GPIO_GetPort(Index);"),
+        "{get_port}"
+    );
+    let get_modes = method(&code, "getModes");
+    assert!(get_modes.contains("const uint32_t &"), "{get_modes}");
+    let get_context = method(&code, "getContext");
+    assert!(get_context.contains("void *"), "{get_context}");
+    assert!(!get_context.contains("return *"), "{get_context}");
+}
@@ -0,0 +1,66 @@
+use sthal_to_cpp::preprocessor_args;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|&arg| arg.to_owned()).collect()
+}
+
+#[test]
+fn only_preprocessor_flags_are_kept() {
+    let command = args(&[
+        "arm-none-eabi-gcc",
+        "-DSTM32F405xx",
+        "-D",
+        "USE_HAL_DRIVER",
+        "-IInc",
+        "-I",
+        "Drivers/Inc",
+        "-std=c11",
+        "-Wall",
+        "-O2",
+        "-c",
+        "-o",
+        "build/stm32f4xx_hal_uart.o",
+        "Src/stm32f4xx_hal_uart.c",
+    ]);
+    assert_eq!(
+        preprocessor_args(command),
+        args(&[
+            "-DSTM32F405xx",
+            "-D",
+            "USE_HAL_DRIVER",
+            "-IInc",
+            "-I",
+            "Drivers/Inc",
+            "-std=c11",
+        ])
+    );
+}
+
+#[test]
+fn prefix_headers_are_kept() {
+    let command = args(&[
+        "cc",
+        "-include",
+        "config.h",
+        "-includeprefix.h",
+        "-c",
+        "x.c",
+    ]);
+    assert_eq!(
+        preprocessor_args(command),
+        args(&["-include", "config.h", "-includeprefix.h"])
+    );
+}
+
+#[test]
+fn precompiled_headers_are_dropped_with_their_path() {
+    let command = args(&[
+        "cc",
+        "-include-pch",
+        "build/pch.h.pch",
+        "-include=odd.h",
+        "-DNDEBUG",
+        "x.c",
+    ]);
+    assert_eq!(preprocessor_args(command), args(&["-DNDEBUG"]));
+}
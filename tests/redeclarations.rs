@@ -0,0 +1,11 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn redeclared_functions_are_wrapped_once() {
+    let code = common::convert("ll_twice", &Options::default());
+    assert_eq!(code.matches("LL_TWICE_Enable(").count(), 1, "{code}");
+    // Along with those of the extension module
+    assert!(code.contains("LL_TWICEEx_Start(this->twice)"), "{code}");
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use sthal_to_cpp::{NamespaceLayout, Options};
+
+#[test]
+fn constants_are_class_members_in_the_flat_layout() {
+    let outdir = common::outdir();
+    let options = Options {
+        reexport_constants: true,
+        ..Options::default()
+    };
+    common::convert_to("hal_uart", &outdir, &options).unwrap();
+    let header = outdir.join("hal_uart.hpp");
+    let code = std::fs::read_to_string(&header).unwrap();
+    assert!(
+        code.contains("static constexpr auto WORDLENGTH_8B = UART_WORDLENGTH_8B;"),
+        "{code}"
+    );
+    assert!(
+        code.contains("static constexpr auto WORDLENGTH_9B = UART_WORDLENGTH_9B;"),
+        "{code}"
+    );
+    // Function-like macros and pointer casts can't be `constexpr` values
+    assert!(!code.contains("FLAG_SET"), "{code}");
+    assert!(!code.contains("DEFAULT_INSTANCE"), "{code}");
+    common::assert_compiles(&header, "hal_uart");
+}
+
+#[test]
+fn constants_are_in_the_peripheral_namespace_otherwise() {
+    let options = Options {
+        reexport_constants: true,
+        namespace_layout: NamespaceLayout::Nested,
+        ..Options::default()
+    };
+    let code = common::convert("hal_uart", &options);
+    assert!(
+        code.contains(
+            "namespace Uart {\n\t\t\tinline constexpr auto WORDLENGTH_8B = UART_WORDLENGTH_8B;"
+        ),
+        "{code}"
+    );
+    assert!(!code.contains("static constexpr"), "{code}");
+}
+
+#[test]
+fn constants_are_left_out_by_default() {
+    let code = common::convert("hal_uart", &Options::default());
+    assert!(!code.contains("constexpr auto"), "{code}");
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn struct_pointers_are_taken_by_reference() {
+    let options = Options {
+        refs: true,
+        ..Options::default()
+    };
+    let code = common::convert("ll_refs", &options);
+    // The reference's address is forwarded, the handle is still passed by pointer
+    assert!(code.contains("configure(REFS_Config &Config)"), "{code}");
+    assert!(
+        code.contains("LL_REFS_Configure(this->refs, &Config)"),
+        "{code}"
+    );
+    assert!(code.contains("apply(REFS_Config &arg0)"), "{code}");
+    assert!(code.contains("LL_REFS_Apply(this->refs, &arg0)"), "{code}");
+    // Const structs, other pointers and arrays are unchanged
+    assert!(code.contains("check(const REFS_Config *Config)"), "{code}");
+    assert!(code.contains("LL_REFS_Check(this->refs, Config)"), "{code}");
+    assert!(
+        code.contains("write(uint8_t *pData, void *Context)"),
+        "{code}"
+    );
+    assert!(
+        code.contains("LL_REFS_Write(this->refs, pData, Context)"),
+        "{code}"
+    );
+    assert!(code.contains("LL_REFS_Fill(this->refs, Configs)"), "{code}");
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use fxhash::FxHashSet;
+use sthal_to_cpp::{NamespaceLayout, Options, StatusMode};
+
+#[test]
+fn unchanged_modules_are_skipped_until_the_options_change() {
+    let outdir = common::outdir();
+    let options = Options {
+        skip_unchanged: true,
+        ..Options::default()
+    };
+
+    let first = common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(first.contains("converted to"), "{first}");
+    let code = std::fs::read_to_string(outdir.join("hal_uart.hpp")).unwrap();
+    assert!(
+        code.starts_with("// Generated by sthal_to_cpp, inputs "),
+        "{code}"
+    );
+
+    let second = common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(second.contains("unchanged"), "{second}");
+
+    let options = Options {
+        status_mode: StatusMode::Bool,
+        ..options
+    };
+    let third = common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(third.contains("converted to"), "{third}");
+}
+
+#[test]
+fn a_changed_functions_from_list_regenerates() {
+    let outdir = common::outdir();
+    std::fs::create_dir_all(&outdir).unwrap();
+    let list = outdir.join("functions.txt");
+    std::fs::write(&list, "HAL_UART_Transmit\n").unwrap();
+    let mut options = Options {
+        skip_unchanged: true,
+        functions_from: Some(list.clone()),
+        ..Options::default()
+    };
+    options.resolve().unwrap();
+
+    let first = common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(first.contains("converted to"), "{first}");
+    let second = common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(second.contains("unchanged"), "{second}");
+
+    std::fs::write(&list, "HAL_UART_Transmit\nHAL_UART_Receive\n").unwrap();
+    options.resolve().unwrap();
+    let third = common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(third.contains("converted to"), "{third}");
+    let code = std::fs::read_to_string(outdir.join("hal_uart.hpp")).unwrap();
+    assert!(code.contains(" receive("), "{code}");
+}
+
+#[test]
+fn the_generated_region_is_stamped_under_append_mode() {
+    let outdir = common::outdir();
+    let options = Options {
+        skip_unchanged: true,
+        append_mode: true,
+        ..Options::default()
+    };
+
+    let first = common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(first.contains("converted to"), "{first}");
+    let path = outdir.join("hal_uart.hpp");
+    let code = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, format!("// Mine\n{code}void mine();\n")).unwrap();
+
+    let second = common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(second.contains("unchanged"), "{second}");
+}
+
+#[test]
+fn a_new_hal_ll_collision_regenerates() {
+    let outdir = common::outdir();
+    let options = Options {
+        skip_unchanged: true,
+        namespace_layout: NamespaceLayout::Merged,
+        ..Options::default()
+    };
+    let convert = |overlap: &[&str]| {
+        let overlap = overlap.iter().map(|&periph| periph.to_owned()).collect();
+        let file = common::fixture("ll_tim");
+        common::convert_among(&file, &outdir, &overlap, &Mutex::default(), &options).unwrap()
+    };
+
+    assert!(convert(&[]).contains("converted to"));
+    assert!(convert(&[]).contains("unchanged"));
+    let third = convert(&["tim"]);
+    assert!(third.contains("converted to"), "{third}");
+    let code = std::fs::read_to_string(outdir.join("ll_tim.hpp")).unwrap();
+    assert!(code.contains("namespace ll {"), "{code}");
+}
+
+#[test]
+fn new_path_roots_regenerate() {
+    let outdir = common::outdir();
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut options = Options {
+        skip_unchanged: true,
+        namespace_from_path: true,
+        path_roots: vec![fixtures.clone()],
+        ..Options::default()
+    };
+
+    let first = common::convert_to("ll_tim", &outdir, &options).unwrap();
+    assert!(first.contains("converted to"), "{first}");
+    let second = common::convert_to("ll_tim", &outdir, &options).unwrap();
+    assert!(second.contains("unchanged"), "{second}");
+
+    options.path_roots = vec![fixtures.parent().unwrap().to_owned()];
+    let third = common::convert_to("ll_tim", &outdir, &options).unwrap();
+    assert!(third.contains("converted to"), "{third}");
+    let code = std::fs::read_to_string(outdir.join("ll_tim.hpp")).unwrap();
+    assert!(code.contains("namespace fixtures {"), "{code}");
+}
+
+#[test]
+fn the_stamp_follows_the_banner() {
+    let outdir = common::outdir();
+    std::fs::create_dir_all(&outdir).unwrap();
+    let banner_file = outdir.join("LICENSE_HEADER");
+    std::fs::write(&banner_file, "// Copyright ACME\n").unwrap();
+    let mut options = Options {
+        skip_unchanged: true,
+        banner_file: Some(banner_file),
+        ..Options::default()
+    };
+    options.resolve().unwrap();
+
+    let first = common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(first.contains("converted to"), "{first}");
+    let code = std::fs::read_to_string(outdir.join("hal_uart.hpp")).unwrap();
+    assert!(
+        code.starts_with("// Copyright ACME\n// Generated by sthal_to_cpp, inputs "),
+        "{code}"
+    );
+    let second = common::convert_to("hal_uart", &outdir, &options).unwrap();
+    assert!(second.contains("unchanged"), "{second}");
+}
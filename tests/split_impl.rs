@@ -0,0 +1,49 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+/// The header and source generated from the fixture of `module` under `--split-impl`.
+fn convert(module: &str) -> (String, String) {
+    let outdir = common::outdir();
+    let options = Options {
+        split_impl: true,
+        ..Options::default()
+    };
+    common::convert_to(module, &outdir, &options).unwrap();
+    let read = |ext| std::fs::read_to_string(outdir.join(format!("{module}.{ext}"))).unwrap();
+    (read("hpp"), read("cpp"))
+}
+
+#[test]
+fn methods_are_defined_in_the_source() {
+    let (header, source) = convert("ll_refs");
+    assert!(
+        header.contains("void configure(REFS_Config *Config);"),
+        "{header}"
+    );
+    assert!(!header.contains("LL_REFS_Configure("), "{header}");
+    assert!(source.contains("#include \"ll_refs.hpp\""), "{source}");
+    assert!(
+        source.contains("void Refs::configure(REFS_Config *Config) {"),
+        "{source}"
+    );
+    assert!(
+        source.contains("LL_REFS_Configure(this->refs, Config)"),
+        "{source}"
+    );
+}
+
+#[test]
+fn functions_without_a_handle_are_defined_in_the_source() {
+    let (header, source) = convert("ll_foo");
+    assert!(
+        header.contains("void setCallback(void (*cb)(void));"),
+        "{header}"
+    );
+    assert!(!header.contains("static"), "{header}");
+    assert!(
+        source.contains("void setCallback(void (*cb)(void)) {"),
+        "{source}"
+    );
+    assert!(source.contains("LL_FOO_SetCallback(cb)"), "{source}");
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::Options;
+
+#[test]
+fn keyword_names_get_an_underscore() {
+    let outdir = common::outdir();
+    common::convert_to("ll_kw", &outdir, &Options::default()).unwrap();
+    let header = outdir.join("ll_kw.hpp");
+    let code = std::fs::read_to_string(&header).unwrap();
+    assert!(
+        method(&code, "delete_").contains("return LL_KW_Delete();"),
+        "{code}"
+    );
+    assert!(
+        method(&code, "new_").contains("new_(uint32_t Size)"),
+        "{code}"
+    );
+    // Without a name it's skipped
+    assert!(!code.contains("LL_KW_()"), "{code}");
+    common::assert_compiles(&header, "ll_kw");
+}
+
+#[test]
+fn strict_names_fail_on_keywords() {
+    let options = Options {
+        strict_names: true,
+        ..Options::default()
+    };
+    let err = common::convert_to("ll_kw", &common::outdir(), &options).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("LL_KW_Delete maps to the C++ keyword `delete`"),
+        "{err}"
+    );
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::Options;
+
+#[test]
+fn elaborated_specifiers_are_stripped() {
+    let code = common::convert("hal_alias", &Options::default());
+    let swap = method(&code, "swap");
+    assert!(
+        swap.contains("__ALIAS_HandleTypeDef * swap(__ALIAS_HandleTypeDef *other)"),
+        "{swap}"
+    );
+    assert!(!code.contains("struct "), "{code}");
+}
+
+#[test]
+fn elaborated_specifiers_can_be_kept() {
+    let options = Options {
+        strip_elaborated: false,
+        ..Options::default()
+    };
+    let code = common::convert("hal_alias", &options);
+    let swap = method(&code, "swap");
+    assert!(
+        swap.contains("struct __ALIAS_HandleTypeDef * swap(struct __ALIAS_HandleTypeDef *other)"),
+        "{swap}"
+    );
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use std::path::PathBuf;
+
+use sthal_to_cpp::{NamespaceLayout, Options};
+
+/// The header generated from the fixture of `module`, and its path.
+fn convert(module: &str, opts: &Options) -> (String, PathBuf) {
+    let outdir = common::outdir();
+    common::convert_to(module, &outdir, opts).unwrap();
+    let header = outdir.join(format!("{module}.hpp"));
+    (std::fs::read_to_string(&header).unwrap(), header)
+}
+
+#[test]
+fn namespaces_close_without_semicolon_and_classes_with() {
+    let (code, header) = convert("hal_uart", &Options::default());
+    // Everything is in a namespace, only its closing brace is unindented
+    assert!(code.lines().any(|line| line == "}"), "{code}");
+    assert!(!code.lines().any(|line| line == "};"), "{code}");
+    assert!(code.lines().any(|line| line == "\t};"), "{code}");
+    common::assert_compiles(&header, "hal_uart");
+}
+
+#[test]
+fn functions_without_a_class_compile() {
+    let (code, header) = convert("ll_dup", &Options::default());
+    assert!(
+        code.contains("namespace ll {\n\tnamespace Dup {\n"),
+        "{code}"
+    );
+    assert!(!code.contains("};"), "{code}");
+    common::assert_compiles(&header, "ll_dup");
+}
+
+#[test]
+fn nested_namespaces_compile() {
+    let options = Options {
+        namespace_layout: NamespaceLayout::Nested,
+        ..Options::default()
+    };
+    let (code, header) = convert("hal_uart", &options);
+    assert!(code.contains("\t\t\t};\n\t\t}\n\t}\n}\n"), "{code}");
+    common::assert_compiles(&header, "hal_uart");
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::{Options, StatusMode};
+
+#[test]
+fn failed_statuses_are_thrown() {
+    let outdir = common::outdir();
+    let options = Options {
+        status_mode: StatusMode::Throw,
+        ..Options::default()
+    };
+    common::convert_to("hal_uart", &outdir, &options).unwrap();
+    let header = outdir.join("hal_uart.hpp");
+    let code = std::fs::read_to_string(&header).unwrap();
+    let error = "namespace hal {\n\tstruct Error { HAL_StatusTypeDef status; };";
+    assert_eq!(code.matches(error).count(), 1, "{code}");
+    // Declared before it's thrown
+    assert!(
+        code.find(error).unwrap() < code.find("throw ").unwrap(),
+        "{code}"
+    );
+    let transmit = method(&code, "transmit");
+    assert!(transmit.contains("void transmit("), "{transmit}");
+    assert!(
+        transmit.contains("if (auto status = HAL_UART_Transmit(")
+            && transmit.contains("status != HAL_OK) { throw ::hal::Error{status}; }"),
+        "{transmit}"
+    );
+    common::assert_compiles(&header, "hal_uart");
+}
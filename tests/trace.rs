@@ -0,0 +1,39 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn calls_are_traced_in_debug_builds() {
+    let options = Options {
+        trace: true,
+        ..Options::default()
+    };
+    let outdir = common::outdir();
+    common::convert_to("hal_uart", &outdir, &options).unwrap();
+    let header = outdir.join("hal_uart.hpp");
+    let code = std::fs::read_to_string(&header).unwrap();
+    // The fallback definition of the default macro
+    assert!(
+        code.contains("#ifndef STHAL_TRACE\n#include <cstdio>\n#define STHAL_TRACE(name) "),
+        "{code}"
+    );
+    assert!(
+        code.contains("#ifndef NDEBUG\n\t\t\tSTHAL_TRACE(\"HAL_UART_Transmit\");\n#endif\n"),
+        "{code}"
+    );
+    common::assert_compiles(&header, "hal_uart");
+}
+
+#[test]
+fn trace_macro_is_configurable() {
+    let options = Options {
+        trace: true,
+        trace_macro: "MY_TRACE".to_owned(),
+        ..Options::default()
+    };
+    let code = common::convert("hal_uart", &options);
+    assert!(code.contains("MY_TRACE(\"HAL_UART_Transmit\");"), "{code}");
+    // Defined by the application
+    assert!(!code.contains("#define MY_TRACE"), "{code}");
+    assert!(!code.contains("STHAL_TRACE"), "{code}");
+}
@@ -0,0 +1,28 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn unnamed_parameters_are_numbered() {
+    let outdir = common::outdir();
+    common::convert_to("ll_anon", &outdir, &Options::default()).unwrap();
+    let header = outdir.join("ll_anon.hpp");
+    let code = std::fs::read_to_string(&header).unwrap();
+    assert!(
+        common::method(&code, "set").contains(
+            "set(uint32_t arg0, uint32_t arg1) { return LL_ANON_Set(this->anon, arg0, arg1); }"
+        ),
+        "{code}"
+    );
+    assert!(
+        common::method(&code, "scale")
+            .contains("scale(uint32_t arg0) { return LL_ANON_Scale(arg0); }"),
+        "{code}"
+    );
+    // The functions after them are still converted
+    assert!(
+        common::method(&code, "enable").contains("LL_ANON_Enable(this->anon)"),
+        "{code}"
+    );
+    common::assert_compiles(&header, "ll_anon");
+}
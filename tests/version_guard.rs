@@ -0,0 +1,27 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn guard_checks_the_parsed_versions() {
+    let outdir = common::outdir();
+    let options = Options {
+        version_guard: true,
+        ..Options::default()
+    };
+    common::convert_to("hal_uart", &outdir, &options).unwrap();
+    let header = outdir.join("hal_uart.hpp");
+    let code = std::fs::read_to_string(&header).unwrap();
+    assert!(
+        code.contains("#if __STM32F4xx_HAL_VERSION_MAIN != (0x01U) || __STM32F4xx_HAL_VERSION_SUB1 != (0x08U) || __STM32F4xx_HAL_VERSION_SUB2 != 0x03U || __STM32F4xx_HAL_VERSION_RC != (0x00U)\n#error \""),
+        "{code}"
+    );
+    // The same version it was generated from passes the guard
+    common::assert_compiles(&header, "hal_uart");
+}
+
+#[test]
+fn no_guard_by_default() {
+    let code = common::convert("hal_uart", &Options::default());
+    assert!(!code.contains("#error"), "{code}");
+}
@@ -0,0 +1,14 @@
+mod common;
+
+use common::method;
+use sthal_to_cpp::Options;
+
+#[test]
+fn volatile_survives_in_signatures() {
+    let code = common::convert("ll_reg", &Options::default());
+    assert!(code.contains("read(volatile uint32_t *pValue)"), "{code}");
+    // `__IO` is expanded
+    assert!(code.contains("write(volatile uint32_t *arg0)"), "{code}");
+    let data = method(&code, "data");
+    assert!(data.contains("volatile uint32_t *"), "{data}");
+}
@@ -0,0 +1,19 @@
+mod common;
+
+use sthal_to_cpp::Options;
+
+#[test]
+fn functions_without_parameters_are_static_or_global() {
+    let code = common::convert("hal_uart", &Options::default());
+    // Named after the peripheral: a static method of its class
+    assert!(
+        code.contains(
+            "static inline uint32_t getInstanceCount() { return HAL_UART_GetInstanceCount(); }"
+        ),
+        "{code}"
+    );
+    // A default `--global-functions` one: in `Core`
+    assert_eq!(code.matches("HAL_GetTick()").count(), 1, "{code}");
+    // Neither: skipped
+    assert!(!code.contains("HAL_GetUid"), "{code}");
+}